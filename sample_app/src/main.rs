@@ -6,6 +6,7 @@ use winit::event::{ElementState, Event, KeyEvent};
 use winit::keyboard::{ModifiersKeyState, NamedKey, PhysicalKey};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
+use graphicat::device::{Device, DeviceParameters};
 use graphicat::gpu::{GpuSelectionParameters, PhysicalDevice};
 use graphicat::instance::{Instance, RwhExtensionProvider};
 use graphicat::surface::Surface;
@@ -14,13 +15,14 @@ fn glfw_main() {
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
 
     let instance = unsafe { Instance::new(&glfw) }.unwrap();
-    let physical_device = PhysicalDevice::select(instance.clone(), GpuSelectionParameters::default()).unwrap();
 
     glfw.window_hint(WindowHint::ClientApi(ClientApiHint::NoApi));
     glfw.window_hint(WindowHint::Resizable(false));
     let (mut window, events) = glfw.create_window(800, 600, "Hello!", WindowMode::Windowed).unwrap();
 
-    let surface = Surface::new_glfw(instance.as_ref(), &window).unwrap();
+    let surface = Surface::new_glfw(instance.clone(), &window).unwrap();
+    let physical_device = PhysicalDevice::select(instance.clone(), GpuSelectionParameters::default().present_to(&surface)).unwrap();
+    let device = Device::new(physical_device.clone(), &DeviceParameters::new().present_to(&surface)).unwrap();
 
     while !window.should_close() {
         glfw.poll_events();
@@ -34,6 +36,7 @@ fn glfw_main() {
 
     println!("GPU: {}", physical_device.name());
     println!("GPU Type: {:?}", physical_device.device_type());
+    println!("Queue families: {:?}", device.queue_families());
 
     println!("Required Extensions: {:?}", graphicat::gpu::required_device_extensions());
 }
@@ -45,10 +48,9 @@ fn winit_main() {
         .with_resizable(false)
         .build(&event_loop).unwrap();
 
-    let instance = unsafe { Instance::new(&RwhExtensionProvider::new(&window)) }.unwrap();
-    let physical_device = PhysicalDevice::select(instance.clone(), GpuSelectionParameters::default()).unwrap();
-
-    let surface = Surface::new(instance.as_ref(), &window).unwrap();
+    let instance = unsafe { Instance::new(&RwhExtensionProvider::new(&window).unwrap()) }.unwrap();
+    let surface = Surface::new(instance.clone(), &window).unwrap();
+    let physical_device = PhysicalDevice::select(instance.clone(), GpuSelectionParameters::default().present_to(&surface)).unwrap();
 
     let mut modifiers = winit::event::Modifiers::default();
 
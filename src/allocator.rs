@@ -0,0 +1,395 @@
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::fmt::{Display, Formatter};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use ash::vk;
+
+/// Host memory allocator used by the Vulkan implementation for its CPU side allocations, given to `InstanceBuilder::allocator`.
+///
+/// Every object created from the instance, surfaces and devices included, is created and destroyed with the same allocator.
+/// Implementations may be called from any thread and must not panic, a panic is caught and reported as a failed allocation.
+pub trait HostAllocator: Send + Sync {
+    /// Allocate `size` bytes aligned to `alignment`, `size` is never 0. Returns null on failure.
+    fn allocate(&self, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void;
+
+    /// Resize an allocation made by this allocator, keeping its content up to the smaller size. `original` is never null and `size` never 0.
+    /// Returns null on failure, in which case `original` must be left untouched.
+    ///
+    /// # Safety
+    /// `original` must be a live allocation returned by this allocator.
+    unsafe fn reallocate(&self, original: *mut c_void, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void;
+
+    /// Free an allocation made by this allocator, `memory` is never null.
+    ///
+    /// # Safety
+    /// `memory` must be a live allocation returned by this allocator.
+    unsafe fn free(&self, memory: *mut c_void);
+
+    /// Notification of an allocation made by the implementation itself, e.g. executable memory.
+    fn internal_allocation(&self, _size: usize, _allocation_type: vk::InternalAllocationType, _scope: vk::SystemAllocationScope) {}
+
+    /// Notification of the release of an allocation reported by `internal_allocation`.
+    fn internal_free(&self, _size: usize, _allocation_type: vk::InternalAllocationType, _scope: vk::SystemAllocationScope) {}
+}
+
+/// The `vk::AllocationCallbacks` of a host allocator, owning the allocator they point to.
+pub(crate) struct AllocationCallbacks {
+    // Boxed so that the address given to Vulkan as user data is stable.
+    allocator: Box<Arc<dyn HostAllocator>>,
+    callbacks: vk::AllocationCallbacks,
+}
+
+// The callbacks only point to the allocator they own, which is `Send + Sync`.
+unsafe impl Send for AllocationCallbacks {}
+unsafe impl Sync for AllocationCallbacks {}
+
+impl AllocationCallbacks {
+    pub(crate) fn new(allocator: Arc<dyn HostAllocator>) -> Self {
+        let allocator = Box::new(allocator);
+        let callbacks = vk::AllocationCallbacks::builder()
+            .user_data(&*allocator as *const Arc<dyn HostAllocator> as *mut c_void)
+            .pfn_allocation(Some(allocation_callback))
+            .pfn_reallocation(Some(reallocation_callback))
+            .pfn_free(Some(free_callback))
+            .pfn_internal_allocation(Some(internal_allocation_callback))
+            .pfn_internal_free(Some(internal_free_callback))
+            .build();
+
+        Self {
+            allocator,
+            callbacks,
+        }
+    }
+
+    pub(crate) fn allocator(&self) -> &Arc<dyn HostAllocator> {
+        &self.allocator
+    }
+
+    pub(crate) fn callbacks(&self) -> &vk::AllocationCallbacks {
+        &self.callbacks
+    }
+}
+
+unsafe fn allocator<'a>(user_data: *mut c_void) -> &'a dyn HostAllocator {
+    &**(user_data as *const Arc<dyn HostAllocator>)
+}
+
+// Unwinding into the Vulkan implementation is undefined behaviour, so a panicking allocator is contained in the callbacks below.
+
+unsafe extern "system" fn allocation_callback(user_data: *mut c_void, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void {
+    if size == 0 {
+        return std::ptr::null_mut();
+    }
+    let allocator = allocator(user_data);
+    std::panic::catch_unwind(AssertUnwindSafe(|| allocator.allocate(size, alignment, scope))).unwrap_or(std::ptr::null_mut())
+}
+
+unsafe extern "system" fn reallocation_callback(user_data: *mut c_void, original: *mut c_void, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void {
+    // Vulkan defines reallocating null as allocating and reallocating to 0 bytes as freeing.
+    if original.is_null() {
+        return allocation_callback(user_data, size, alignment, scope);
+    }
+    if size == 0 {
+        free_callback(user_data, original);
+        return std::ptr::null_mut();
+    }
+    let allocator = allocator(user_data);
+    std::panic::catch_unwind(AssertUnwindSafe(|| allocator.reallocate(original, size, alignment, scope))).unwrap_or(std::ptr::null_mut())
+}
+
+unsafe extern "system" fn free_callback(user_data: *mut c_void, memory: *mut c_void) {
+    if memory.is_null() {
+        return;
+    }
+    let allocator = allocator(user_data);
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| allocator.free(memory)));
+}
+
+unsafe extern "system" fn internal_allocation_callback(user_data: *mut c_void, size: usize, allocation_type: vk::InternalAllocationType, scope: vk::SystemAllocationScope) {
+    let allocator = allocator(user_data);
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| allocator.internal_allocation(size, allocation_type, scope)));
+}
+
+unsafe extern "system" fn internal_free_callback(user_data: *mut c_void, size: usize, allocation_type: vk::InternalAllocationType, scope: vk::SystemAllocationScope) {
+    let allocator = allocator(user_data);
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| allocator.internal_free(size, allocation_type, scope)));
+}
+
+const SCOPES: [vk::SystemAllocationScope; 5] = [
+    vk::SystemAllocationScope::COMMAND,
+    vk::SystemAllocationScope::OBJECT,
+    vk::SystemAllocationScope::CACHE,
+    vk::SystemAllocationScope::DEVICE,
+    vk::SystemAllocationScope::INSTANCE,
+];
+
+fn scope_index(scope: vk::SystemAllocationScope) -> usize {
+    SCOPES.iter().position(|s| *s == scope).unwrap_or(SCOPES.len() - 1)
+}
+
+/// Memory accounting of one allocation scope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScopeStats {
+    /// Bytes currently allocated.
+    pub live_bytes: usize,
+    /// Highest value reached by `live_bytes`.
+    pub peak_bytes: usize,
+    /// Allocations currently alive.
+    pub live_allocations: usize,
+    /// Allocations made since the allocator was created, reallocations included.
+    pub total_allocations: u64,
+    /// Bytes allocated by the implementation itself and reported through internal allocation notifications.
+    pub internal_bytes: usize,
+}
+
+/// Memory accounting of a `TrackingAllocator`, per `vk::SystemAllocationScope`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    scopes: [ScopeStats; 5],
+    peak_bytes: usize,
+}
+
+impl AllocationStats {
+    pub fn scope(&self, scope: vk::SystemAllocationScope) -> ScopeStats {
+        self.scopes[scope_index(scope)]
+    }
+
+    /// Bytes currently allocated across all scopes.
+    pub fn live_bytes(&self) -> usize {
+        self.scopes.iter().map(|scope| scope.live_bytes).sum()
+    }
+
+    /// Highest value reached by `live_bytes`, which can be lower than the sum of the per scope peaks.
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    pub fn live_allocations(&self) -> usize {
+        self.scopes.iter().map(|scope| scope.live_allocations).sum()
+    }
+
+    pub fn total_allocations(&self) -> u64 {
+        self.scopes.iter().map(|scope| scope.total_allocations).sum()
+    }
+
+    fn allocated(&mut self, size: usize, scope: vk::SystemAllocationScope) {
+        let stats = &mut self.scopes[scope_index(scope)];
+        stats.live_bytes += size;
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+        stats.live_allocations += 1;
+        stats.total_allocations += 1;
+        self.peak_bytes = self.peak_bytes.max(self.live_bytes());
+    }
+
+    fn freed(&mut self, size: usize, scope: vk::SystemAllocationScope) {
+        let stats = &mut self.scopes[scope_index(scope)];
+        stats.live_bytes -= size;
+        stats.live_allocations -= 1;
+    }
+}
+
+impl Display for AllocationStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} bytes in {} allocations, peak {} bytes, {} allocations in total", self.live_bytes(), self.live_allocations(), self.peak_bytes, self.total_allocations())?;
+        for (scope, stats) in SCOPES.iter().zip(&self.scopes) {
+            writeln!(f, "  {:?}: {} bytes in {} allocations, peak {} bytes, {} allocations in total, {} internal bytes",
+                scope, stats.live_bytes, stats.live_allocations, stats.peak_bytes, stats.total_allocations, stats.internal_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+// Stored in front of every allocation of a `TrackingAllocator` to find its layout and scope when it is freed.
+struct AllocationHeader {
+    layout: Layout,
+    size: usize,
+    scope: vk::SystemAllocationScope,
+}
+
+/// A `HostAllocator` allocating from the Rust global allocator and keeping track of the memory allocated per scope.
+#[derive(Default)]
+pub struct TrackingAllocator {
+    stats: Mutex<AllocationStats>,
+}
+
+impl TrackingAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A snapshot of the current memory accounting.
+    pub fn stats(&self) -> AllocationStats {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut AllocationStats)) {
+        f(&mut self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    }
+
+    // The header is placed right before the returned pointer, the offset keeping the pointer aligned.
+    fn header_offset(alignment: usize) -> usize {
+        let alignment = alignment.max(std::mem::align_of::<AllocationHeader>());
+        std::mem::size_of::<AllocationHeader>().div_ceil(alignment) * alignment
+    }
+
+    unsafe fn header<'a>(memory: *mut c_void) -> &'a AllocationHeader {
+        &*(memory as *const AllocationHeader).sub(1)
+    }
+}
+
+impl HostAllocator for TrackingAllocator {
+    fn allocate(&self, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void {
+        let offset = Self::header_offset(alignment);
+        let layout = match offset.checked_add(size).and_then(|total| Layout::from_size_align(total, alignment.max(std::mem::align_of::<AllocationHeader>())).ok()) {
+            Some(layout) => layout,
+            None => return std::ptr::null_mut(),
+        };
+
+        unsafe {
+            let base = std::alloc::alloc(layout);
+            if base.is_null() {
+                return std::ptr::null_mut();
+            }
+            let memory = base.add(offset) as *mut c_void;
+            (memory as *mut AllocationHeader).sub(1).write(AllocationHeader {
+                layout,
+                size,
+                scope,
+            });
+
+            self.update(|stats| stats.allocated(size, scope));
+            memory
+        }
+    }
+
+    unsafe fn reallocate(&self, original: *mut c_void, size: usize, alignment: usize, scope: vk::SystemAllocationScope) -> *mut c_void {
+        let memory = self.allocate(size, alignment, scope);
+        if memory.is_null() {
+            return memory;
+        }
+
+        let original_size = Self::header(original).size;
+        std::ptr::copy_nonoverlapping(original as *const u8, memory as *mut u8, original_size.min(size));
+        self.free(original);
+        memory
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        let header = Self::header(memory);
+        let (layout, size, scope) = (header.layout, header.size, header.scope);
+        let base = (memory as *mut u8).sub(Self::header_offset(layout.align()));
+        std::alloc::dealloc(base, layout);
+
+        self.update(|stats| stats.freed(size, scope));
+    }
+
+    fn internal_allocation(&self, size: usize, _allocation_type: vk::InternalAllocationType, scope: vk::SystemAllocationScope) {
+        self.update(|stats| stats.scopes[scope_index(scope)].internal_bytes += size);
+    }
+
+    fn internal_free(&self, size: usize, _allocation_type: vk::InternalAllocationType, scope: vk::SystemAllocationScope) {
+        self.update(|stats| {
+            let internal_bytes = &mut stats.scopes[scope_index(scope)].internal_bytes;
+            *internal_bytes = internal_bytes.saturating_sub(size);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_void;
+    use std::sync::Arc;
+    use ash::vk;
+    use super::{AllocationCallbacks, HostAllocator, TrackingAllocator};
+
+    const COMMAND: vk::SystemAllocationScope = vk::SystemAllocationScope::COMMAND;
+    const OBJECT: vk::SystemAllocationScope = vk::SystemAllocationScope::OBJECT;
+
+    #[test]
+    fn allocations_are_aligned_and_accounted() {
+        let allocator = TrackingAllocator::new();
+        let allocations: Vec<*mut c_void> = [1, 8, 16, 64, 256, 4096].into_iter()
+            .map(|alignment| {
+                let memory = allocator.allocate(24, alignment, OBJECT);
+                assert!(!memory.is_null());
+                assert_eq!(memory as usize % alignment, 0, "not aligned to {}", alignment);
+                // The whole allocation is writable without overwriting the header.
+                unsafe { std::ptr::write_bytes(memory as *mut u8, 0xab, 24) };
+                memory
+            })
+            .collect();
+
+        let stats = allocator.stats();
+        assert_eq!(stats.live_bytes(), 6 * 24);
+        assert_eq!(stats.live_allocations(), 6);
+        assert_eq!(stats.scope(OBJECT).live_allocations, 6);
+        assert_eq!(stats.scope(COMMAND).live_allocations, 0);
+
+        for memory in allocations {
+            unsafe { allocator.free(memory) };
+        }
+        let stats = allocator.stats();
+        assert_eq!(stats.live_bytes(), 0);
+        assert_eq!(stats.live_allocations(), 0);
+        assert_eq!(stats.peak_bytes(), 6 * 24);
+        assert_eq!(stats.total_allocations(), 6);
+    }
+
+    #[test]
+    fn reallocation_copies_and_accounts() {
+        let allocator = TrackingAllocator::new();
+        unsafe {
+            let memory = allocator.allocate(16, 16, COMMAND) as *mut u8;
+            for i in 0..16 {
+                memory.add(i).write(i as u8);
+            }
+
+            let grown = allocator.reallocate(memory as *mut c_void, 1024, 64, COMMAND) as *mut u8;
+            assert_eq!(grown as usize % 64, 0);
+            assert_eq!(std::slice::from_raw_parts(grown, 16), (0..16).collect::<Vec<u8>>());
+            assert_eq!(allocator.stats().scope(COMMAND).live_bytes, 1024);
+            assert_eq!(allocator.stats().scope(COMMAND).live_allocations, 1);
+
+            let shrunk = allocator.reallocate(grown as *mut c_void, 4, 8, COMMAND) as *mut u8;
+            assert_eq!(std::slice::from_raw_parts(shrunk, 4), [0, 1, 2, 3]);
+            assert_eq!(allocator.stats().scope(COMMAND).live_bytes, 4);
+
+            allocator.free(shrunk as *mut c_void);
+        }
+
+        let stats = allocator.stats().scope(COMMAND);
+        assert_eq!((stats.live_bytes, stats.live_allocations, stats.peak_bytes, stats.total_allocations), (0, 0, 1040, 3));
+    }
+
+    #[test]
+    fn callbacks_follow_vulkan_rules() {
+        let allocator = Arc::new(TrackingAllocator::new());
+        let callbacks = AllocationCallbacks::new(allocator.clone());
+        let callbacks = callbacks.callbacks();
+        let (allocate, reallocate, free) = (callbacks.pfn_allocation.unwrap(), callbacks.pfn_reallocation.unwrap(), callbacks.pfn_free.unwrap());
+        let user_data = callbacks.p_user_data;
+
+        unsafe {
+            free(user_data, std::ptr::null_mut());
+            assert!(allocate(user_data, 0, 8, OBJECT).is_null());
+
+            // Reallocating null allocates, reallocating to 0 bytes frees.
+            let memory = reallocate(user_data, std::ptr::null_mut(), 32, 8, OBJECT);
+            assert!(!memory.is_null());
+            assert_eq!(allocator.stats().live_bytes(), 32);
+            assert!(reallocate(user_data, memory, 0, 8, OBJECT).is_null());
+        }
+        assert_eq!(allocator.stats().live_bytes(), 0);
+        assert_eq!(allocator.stats().live_allocations(), 0);
+    }
+
+    #[test]
+    fn internal_allocations_are_accounted_per_scope() {
+        let allocator = TrackingAllocator::new();
+        allocator.internal_allocation(100, vk::InternalAllocationType::EXECUTABLE, vk::SystemAllocationScope::DEVICE);
+        assert_eq!(allocator.stats().scope(vk::SystemAllocationScope::DEVICE).internal_bytes, 100);
+        allocator.internal_free(150, vk::InternalAllocationType::EXECUTABLE, vk::SystemAllocationScope::DEVICE);
+        assert_eq!(allocator.stats().scope(vk::SystemAllocationScope::DEVICE).internal_bytes, 0);
+    }
+}
@@ -0,0 +1,355 @@
+use ash::prelude::VkResult;
+use ash::vk;
+use crate::features::DeviceFeatures;
+
+/// Properties only available through `vkGetPhysicalDeviceProperties2`, `None` when not queried.
+#[derive(Clone)]
+pub(crate) struct ExtendedProperties {
+    pub properties: vk::PhysicalDeviceProperties,
+    pub id: Option<vk::PhysicalDeviceIDProperties>,
+    pub driver: Option<vk::PhysicalDeviceDriverProperties>,
+    pub pci_bus: Option<vk::PhysicalDevicePCIBusInfoPropertiesEXT>,
+    pub vulkan_1_1: Option<vk::PhysicalDeviceVulkan11Properties>,
+    pub vulkan_1_2: Option<vk::PhysicalDeviceVulkan12Properties>,
+    pub vulkan_1_3: Option<vk::PhysicalDeviceVulkan13Properties>,
+}
+
+// The `p_next` pointers are reset before the structures are returned and never followed, like those of `DeviceFeatures`.
+unsafe impl Send for ExtendedProperties {}
+unsafe impl Sync for ExtendedProperties {}
+
+/// The physical device queries graphicat makes, implemented by `ash::Instance` and by a fake backend in tests.
+pub(crate) trait Backend: Send + Sync {
+    fn physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>>;
+
+    fn properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceProperties;
+
+    /// The ID properties are queried from Vulkan 1.1, the driver and the 1.1 and 1.2 properties from Vulkan 1.2, the 1.3 properties from Vulkan 1.3
+    /// and the PCI bus properties when `pci_bus_info` is true.
+    fn extended_properties(&self, physical_device: vk::PhysicalDevice, api_version: u32, pci_bus_info: bool) -> ExtendedProperties;
+
+    fn memory_properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceMemoryProperties;
+
+    /// See `DeviceFeatures::query`.
+    fn features(&self, physical_device: vk::PhysicalDevice, api_version: u32) -> DeviceFeatures;
+
+    fn extensions(&self, physical_device: vk::PhysicalDevice) -> VkResult<Vec<vk::ExtensionProperties>>;
+
+    fn queue_families(&self, physical_device: vk::PhysicalDevice) -> Vec<vk::QueueFamilyProperties>;
+
+    fn format_properties(&self, physical_device: vk::PhysicalDevice, format: vk::Format) -> vk::FormatProperties;
+}
+
+impl Backend for ash::Instance {
+    fn physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>> {
+        unsafe { self.enumerate_physical_devices() }
+    }
+
+    fn properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceProperties {
+        unsafe { self.get_physical_device_properties(physical_device) }
+    }
+
+    fn extended_properties(&self, physical_device: vk::PhysicalDevice, api_version: u32, pci_bus_info: bool) -> ExtendedProperties {
+        if api_version < vk::API_VERSION_1_1 {
+            return ExtendedProperties {
+                properties: self.properties(physical_device),
+                id: None,
+                driver: None,
+                pci_bus: None,
+                vulkan_1_1: None,
+                vulkan_1_2: None,
+                vulkan_1_3: None,
+            };
+        }
+
+        let mut id = vk::PhysicalDeviceIDProperties::default();
+        let mut driver = vk::PhysicalDeviceDriverProperties::default();
+        let mut pci_bus = vk::PhysicalDevicePCIBusInfoPropertiesEXT::default();
+        let mut vulkan_1_1 = vk::PhysicalDeviceVulkan11Properties::default();
+        let mut vulkan_1_2 = vk::PhysicalDeviceVulkan12Properties::default();
+        let mut vulkan_1_3 = vk::PhysicalDeviceVulkan13Properties::default();
+
+        let mut properties2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut id);
+        if api_version >= vk::API_VERSION_1_2 {
+            properties2 = properties2.push_next(&mut driver).push_next(&mut vulkan_1_1).push_next(&mut vulkan_1_2);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            properties2 = properties2.push_next(&mut vulkan_1_3);
+        }
+        if pci_bus_info {
+            properties2 = properties2.push_next(&mut pci_bus);
+        }
+        unsafe {
+            self.get_physical_device_properties2(physical_device, &mut properties2);
+        }
+        let properties = properties2.properties;
+
+        // Like `DeviceFeatures`, the structures must not keep pointers to each other once returned.
+        id.p_next = std::ptr::null_mut();
+        driver.p_next = std::ptr::null_mut();
+        pci_bus.p_next = std::ptr::null_mut();
+        vulkan_1_1.p_next = std::ptr::null_mut();
+        vulkan_1_2.p_next = std::ptr::null_mut();
+        vulkan_1_3.p_next = std::ptr::null_mut();
+
+        ExtendedProperties {
+            properties,
+            id: Some(id),
+            driver: (api_version >= vk::API_VERSION_1_2).then_some(driver),
+            pci_bus: pci_bus_info.then_some(pci_bus),
+            vulkan_1_1: (api_version >= vk::API_VERSION_1_2).then_some(vulkan_1_1),
+            vulkan_1_2: (api_version >= vk::API_VERSION_1_2).then_some(vulkan_1_2),
+            vulkan_1_3: (api_version >= vk::API_VERSION_1_3).then_some(vulkan_1_3),
+        }
+    }
+
+    fn memory_properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceMemoryProperties {
+        unsafe { self.get_physical_device_memory_properties(physical_device) }
+    }
+
+    fn features(&self, physical_device: vk::PhysicalDevice, api_version: u32) -> DeviceFeatures {
+        let mut features = DeviceFeatures::default();
+        if api_version < vk::API_VERSION_1_1 {
+            features.vulkan_1_0 = unsafe { self.get_physical_device_features(physical_device) };
+            return features;
+        }
+
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder();
+        if api_version >= vk::API_VERSION_1_2 {
+            features2 = features2.push_next(&mut features.vulkan_1_1).push_next(&mut features.vulkan_1_2);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            features2 = features2.push_next(&mut features.vulkan_1_3);
+        }
+
+        let mut features2 = features2.build();
+        unsafe {
+            self.get_physical_device_features2(physical_device, &mut features2);
+        }
+        features.vulkan_1_0 = features2.features;
+
+        features.clear_chain();
+        features
+    }
+
+    fn extensions(&self, physical_device: vk::PhysicalDevice) -> VkResult<Vec<vk::ExtensionProperties>> {
+        unsafe { self.enumerate_device_extension_properties(physical_device) }
+    }
+
+    fn queue_families(&self, physical_device: vk::PhysicalDevice) -> Vec<vk::QueueFamilyProperties> {
+        unsafe { self.get_physical_device_queue_family_properties(physical_device) }
+    }
+
+    fn format_properties(&self, physical_device: vk::PhysicalDevice, format: vk::Format) -> vk::FormatProperties {
+        unsafe { self.get_physical_device_format_properties(physical_device, format) }
+    }
+}
+
+/// A backend describing synthetic devices, to test selection without a Vulkan driver.
+#[cfg(test)]
+pub(crate) mod fake {
+    use std::collections::HashMap;
+    use std::ffi::{c_char, CStr, CString};
+    use std::sync::Arc;
+    use ash::prelude::VkResult;
+    use ash::vk::{self, Handle};
+    use crate::features::DeviceFeatures;
+    use crate::gpu::{required_device_extensions, PhysicalDevice};
+    use crate::instance::Instance;
+    use crate::util::HandleOwnership;
+    use super::{Backend, ExtendedProperties};
+
+    /// A synthetic device: Vulkan 1.3, 1 GiB of VRAM, `required_device_extensions()` and one queue family supporting everything.
+    #[derive(Clone)]
+    pub(crate) struct FakeDevice {
+        pub properties: vk::PhysicalDeviceProperties,
+        pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+        pub features: DeviceFeatures,
+        pub extensions: Vec<CString>,
+        pub queue_families: Vec<vk::QueueFamilyProperties>,
+        pub formats: HashMap<vk::Format, vk::FormatProperties>,
+    }
+
+    impl FakeDevice {
+        pub fn new(name: &str, device_type: vk::PhysicalDeviceType) -> Self {
+            let mut properties = vk::PhysicalDeviceProperties {
+                api_version: vk::API_VERSION_1_3,
+                device_type,
+                ..Default::default()
+            };
+            for (dst, src) in properties.device_name.iter_mut().zip(name.bytes()) {
+                *dst = src as c_char;
+            }
+
+            Self {
+                properties,
+                memory_properties: vk::PhysicalDeviceMemoryProperties::default(),
+                features: DeviceFeatures::default(),
+                extensions: required_device_extensions().into_iter().map(CStr::to_owned).collect(),
+                queue_families: vec![vk::QueueFamilyProperties {
+                    queue_flags: vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER,
+                    queue_count: 1,
+                    ..Default::default()
+                }],
+                formats: HashMap::new(),
+            }
+            .vram_mib(1024)
+        }
+
+        pub fn api_version(mut self, api_version: u32) -> Self {
+            self.properties.api_version = api_version;
+            self
+        }
+
+        /// Replace the memory heaps by one device local heap of the given size.
+        pub fn vram_mib(mut self, mib: u64) -> Self {
+            self.memory_properties.memory_heap_count = 1;
+            self.memory_properties.memory_heaps[0] = vk::MemoryHeap {
+                size: mib * 1024 * 1024,
+                flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+            };
+            self
+        }
+
+        pub fn extension(mut self, extension: &CStr) -> Self {
+            self.extensions.push(extension.to_owned());
+            self
+        }
+
+        pub fn without_extension(mut self, extension: &CStr) -> Self {
+            self.extensions.retain(|e| e.as_c_str() != extension);
+            self
+        }
+
+        pub fn feature(mut self, name: &str) -> Self {
+            assert!(self.features.set(name, true), "unknown feature {}", name);
+            self
+        }
+
+        pub fn limits(mut self, f: impl FnOnce(&mut vk::PhysicalDeviceLimits)) -> Self {
+            f(&mut self.properties.limits);
+            self
+        }
+
+        pub fn queue_families(mut self, queue_families: &[(vk::QueueFlags, u32)]) -> Self {
+            self.queue_families = queue_families.iter()
+                .map(|&(queue_flags, queue_count)| vk::QueueFamilyProperties {
+                    queue_flags,
+                    queue_count,
+                    ..Default::default()
+                })
+                .collect();
+            self
+        }
+
+        /// Support `format` with the given optimal tiling features.
+        pub fn format(mut self, format: vk::Format, optimal_tiling_features: vk::FormatFeatureFlags) -> Self {
+            self.formats.insert(format, vk::FormatProperties {
+                optimal_tiling_features,
+                ..Default::default()
+            });
+            self
+        }
+
+        pub fn ids(mut self, vendor_id: u32, device_id: u32) -> Self {
+            self.properties.vendor_id = vendor_id;
+            self.properties.device_id = device_id;
+            self
+        }
+    }
+
+    pub(crate) struct FakeBackend {
+        devices: Vec<FakeDevice>,
+    }
+
+    impl FakeBackend {
+        // Handles are the index of the device plus one, so that none of them is null.
+        fn device(&self, physical_device: vk::PhysicalDevice) -> &FakeDevice {
+            &self.devices[physical_device.as_raw() as usize - 1]
+        }
+    }
+
+    impl Backend for FakeBackend {
+        fn physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>> {
+            Ok((1..=self.devices.len() as u64).map(vk::PhysicalDevice::from_raw).collect())
+        }
+
+        fn properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceProperties {
+            self.device(physical_device).properties
+        }
+
+        fn extended_properties(&self, physical_device: vk::PhysicalDevice, api_version: u32, _pci_bus_info: bool) -> ExtendedProperties {
+            let id = vk::PhysicalDeviceIDProperties {
+                device_uuid: [physical_device.as_raw() as u8; 16],
+                ..Default::default()
+            };
+            ExtendedProperties {
+                properties: self.properties(physical_device),
+                id: (api_version >= vk::API_VERSION_1_1).then_some(id),
+                driver: None,
+                pci_bus: None,
+                vulkan_1_1: (api_version >= vk::API_VERSION_1_2).then(vk::PhysicalDeviceVulkan11Properties::default),
+                vulkan_1_2: (api_version >= vk::API_VERSION_1_2).then(vk::PhysicalDeviceVulkan12Properties::default),
+                vulkan_1_3: (api_version >= vk::API_VERSION_1_3).then(vk::PhysicalDeviceVulkan13Properties::default),
+            }
+        }
+
+        fn memory_properties(&self, physical_device: vk::PhysicalDevice) -> vk::PhysicalDeviceMemoryProperties {
+            self.device(physical_device).memory_properties
+        }
+
+        // Structures the real query would not chain for `api_version` are left empty.
+        fn features(&self, physical_device: vk::PhysicalDevice, api_version: u32) -> DeviceFeatures {
+            let mut features = self.device(physical_device).features;
+            if api_version < vk::API_VERSION_1_2 {
+                features.vulkan_1_1 = Default::default();
+                features.vulkan_1_2 = Default::default();
+            }
+            if api_version < vk::API_VERSION_1_3 {
+                features.vulkan_1_3 = Default::default();
+            }
+            features
+        }
+
+        fn extensions(&self, physical_device: vk::PhysicalDevice) -> VkResult<Vec<vk::ExtensionProperties>> {
+            Ok(self.device(physical_device).extensions.iter()
+                .map(|name| {
+                    let mut extension = vk::ExtensionProperties::default();
+                    for (dst, src) in extension.extension_name.iter_mut().zip(name.as_bytes()) {
+                        *dst = *src as c_char;
+                    }
+                    extension
+                })
+                .collect())
+        }
+
+        fn queue_families(&self, physical_device: vk::PhysicalDevice) -> Vec<vk::QueueFamilyProperties> {
+            self.device(physical_device).queue_families.clone()
+        }
+
+        fn format_properties(&self, physical_device: vk::PhysicalDevice, format: vk::Format) -> vk::FormatProperties {
+            self.device(physical_device).formats.get(&format).copied().unwrap_or_default()
+        }
+    }
+
+    unsafe extern "system" fn get_instance_proc_addr(_instance: vk::Instance, _name: *const c_char) -> vk::PFN_vkVoidFunction {
+        None
+    }
+
+    /// A Vulkan 1.3 instance enumerating `devices`. Any Vulkan call not going through the backend panics.
+    pub(crate) fn instance(devices: Vec<FakeDevice>) -> Arc<Instance> {
+        let static_fn = vk::StaticFn {
+            get_instance_proc_addr,
+        };
+        unsafe {
+            let entry = ash::Entry::from_static_fn(static_fn.clone());
+            let instance = ash::Instance::load(&static_fn, vk::Instance::null());
+            Instance::from_backend(entry, instance, Box::new(FakeBackend { devices }), vk::API_VERSION_1_3, HandleOwnership::Imported)
+        }
+    }
+
+    /// The only device of a fake instance enumerating `device`.
+    pub(crate) fn physical_device(device: FakeDevice) -> PhysicalDevice {
+        PhysicalDevice::wrap(vk::PhysicalDevice::from_raw(1), instance(vec![device]))
+    }
+}
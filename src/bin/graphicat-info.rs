@@ -0,0 +1,384 @@
+//! Prints what Vulkan reports about this machine and how graphicat would select a device, as text or JSON.
+
+use std::ffi::{CStr, CString};
+use std::process::ExitCode;
+use std::sync::Arc;
+use ash::vk;
+use serde::Serialize;
+use graphicat::device_override::DeviceOverride;
+use graphicat::features::DeviceFeatures;
+use graphicat::gpu::{GpuSelectionParameters, PhysicalDevice};
+use graphicat::info::{core_formats, DeviceInfo};
+use graphicat::instance::Instance;
+use graphicat::profiles::{Profile, Profiles};
+use graphicat::report::SelectionReport;
+use graphicat::util::format_api_version;
+
+const USAGE: &str = "\
+Usage: graphicat-info [options]
+
+Output:
+    --json                    print the report as JSON
+    --summary                 leave out limits, features, extensions and formats in text output
+
+Selection:
+    --headless                do not require VK_KHR_swapchain
+    --type TYPE               only allow devices of this type: discrete, integrated, virtual, cpu or other (repeatable)
+    --require-extension NAME  require a device extension (repeatable)
+    --require-feature NAME    require a device feature, as name or group.name (repeatable)
+    --min-api VERSION         require a Vulkan version, e.g. 1.2
+    --profile FILE            require the profiles of a Vulkan Profiles JSON file (repeatable)
+    --profile-name NAME       only require the profile with this name from the profile files
+    --device OVERRIDE         pin a device, with the syntax of GRAPHICAT_DEVICE
+    --ignore-env              ignore GRAPHICAT_DEVICE
+
+    -h, --help                print this help
+";
+
+#[derive(Default)]
+struct Options {
+    json: bool,
+    summary: bool,
+    headless: bool,
+    types: Vec<vk::PhysicalDeviceType>,
+    extensions: Vec<CString>,
+    features: DeviceFeatures,
+    min_api_version: Option<u32>,
+    profiles: Vec<Profile>,
+    device_override: Option<DeviceOverride>,
+    ignore_env: bool,
+}
+
+fn parse_device_type(value: &str) -> Result<vk::PhysicalDeviceType, String> {
+    match value {
+        "discrete" => Ok(vk::PhysicalDeviceType::DISCRETE_GPU),
+        "integrated" => Ok(vk::PhysicalDeviceType::INTEGRATED_GPU),
+        "virtual" => Ok(vk::PhysicalDeviceType::VIRTUAL_GPU),
+        "cpu" => Ok(vk::PhysicalDeviceType::CPU),
+        "other" => Ok(vk::PhysicalDeviceType::OTHER),
+        _ => Err(format!("unknown device type {:?}", value)),
+    }
+}
+
+fn parse_api_version(value: &str) -> Result<u32, String> {
+    let mut parts = value.split('.').map(|part| part.parse::<u32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), None) => Ok(vk::make_api_version(0, major, minor, 0)),
+        _ => Err(format!("invalid Vulkan version {:?}, expected MAJOR.MINOR", value)),
+    }
+}
+
+/// `Ok(None)` when the help was requested.
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut profile_files = vec![];
+    let mut profile_name = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} expects a value", arg));
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--summary" => options.summary = true,
+            "--headless" => options.headless = true,
+            "--type" => options.types.push(parse_device_type(&value()?)?),
+            "--require-extension" => options.extensions.push(CString::new(value()?).map_err(|error| error.to_string())?),
+            "--require-feature" => {
+                let feature = value()?;
+                if !options.features.set(&feature, true) {
+                    return Err(format!("unknown feature {:?}", feature));
+                }
+            }
+            "--min-api" => options.min_api_version = Some(parse_api_version(&value()?)?),
+            "--profile" => profile_files.push(Profiles::load(value()?).map_err(|error| error.to_string())?),
+            "--profile-name" => profile_name = Some(value()?),
+            "--device" => options.device_override = Some(value()?.parse().map_err(|error: graphicat::Error| error.to_string())?),
+            "--ignore-env" => options.ignore_env = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown option {:?}", arg)),
+        }
+    }
+
+    for profiles in profile_files {
+        match &profile_name {
+            Some(name) => options.profiles.push(profiles.get(name).map_err(|error| error.to_string())?),
+            None => options.profiles.extend(profiles.profiles().iter().cloned()),
+        }
+    }
+
+    Ok(Some(options))
+}
+
+fn selection_parameters(options: &Options) -> GpuSelectionParameters<'_> {
+    let mut params = if options.headless { GpuSelectionParameters::headless() } else { GpuSelectionParameters::new() };
+    if !options.types.is_empty() {
+        params = params.only_types(&options.types);
+    }
+    params = params
+        .require_extensions(options.extensions.iter().map(|extension| extension.as_c_str()))
+        .require_features(options.features)
+        .env_override(!options.ignore_env);
+    if let Some(min_api_version) = options.min_api_version {
+        params = params.min_api_version(min_api_version);
+    }
+    for profile in &options.profiles {
+        params = params.require_profile(profile.clone());
+    }
+    if let Some(device_override) = &options.device_override {
+        params = params.device_override(device_override.clone());
+    }
+    params
+}
+
+#[derive(Serialize)]
+struct Property {
+    name: String,
+    version: String,
+    description: String,
+}
+
+#[derive(Serialize)]
+struct InstanceReport {
+    api_version: String,
+    loader_version: String,
+    loader: String,
+    layers: Vec<Property>,
+    extensions: Vec<Property>,
+}
+
+#[derive(Serialize)]
+struct FormatReport {
+    format: String,
+    linear_tiling: String,
+    optimal_tiling: String,
+    buffer: String,
+}
+
+#[derive(Serialize)]
+struct DeviceEntry {
+    index: usize,
+    info: DeviceInfo,
+    formats: Vec<FormatReport>,
+}
+
+#[derive(Serialize)]
+struct DeviceVerdict {
+    index: usize,
+    name: String,
+    compatible: bool,
+    score: Option<i64>,
+    rejections: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SelectionVerdict {
+    device_override: Option<String>,
+    selected: Option<usize>,
+    error: Option<String>,
+    devices: Vec<DeviceVerdict>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    instance: InstanceReport,
+    devices: Vec<DeviceEntry>,
+    selection: SelectionVerdict,
+}
+
+fn c_string(chars: &[std::ffi::c_char]) -> String {
+    unsafe { CStr::from_ptr(chars.as_ptr()) }.to_string_lossy().into_owned()
+}
+
+fn instance_report(instance: &Instance) -> InstanceReport {
+    let entry = instance.entry();
+    let layers = entry.enumerate_instance_layer_properties().unwrap_or_default().iter()
+        .map(|layer| Property {
+            name: c_string(&layer.layer_name),
+            version: format_api_version(layer.spec_version),
+            description: c_string(&layer.description),
+        })
+        .collect();
+    let extensions = entry.enumerate_instance_extension_properties(None).unwrap_or_default().iter()
+        .map(|extension| Property {
+            name: c_string(&extension.extension_name),
+            version: extension.spec_version.to_string(),
+            description: String::new(),
+        })
+        .collect();
+
+    InstanceReport {
+        api_version: format_api_version(instance.api_version()),
+        loader_version: format_api_version(instance.loader_version()),
+        loader: instance.loader_info().to_string(),
+        layers,
+        extensions,
+    }
+}
+
+fn format_report(physical_device: &PhysicalDevice) -> Vec<FormatReport> {
+    core_formats()
+        .filter_map(|format| {
+            let properties = unsafe { physical_device.instance().get_physical_device_format_properties(physical_device.handle(), format) };
+            let supported = !properties.linear_tiling_features.is_empty() || !properties.optimal_tiling_features.is_empty() || !properties.buffer_features.is_empty();
+            supported.then(|| FormatReport {
+                format: format!("{:?}", format),
+                linear_tiling: format!("{:?}", properties.linear_tiling_features),
+                optimal_tiling: format!("{:?}", properties.optimal_tiling_features),
+                buffer: format!("{:?}", properties.buffer_features),
+            })
+        })
+        .collect()
+}
+
+fn selection_verdict(instance: &Arc<Instance>, options: &Options) -> SelectionVerdict {
+    let params = selection_parameters(options);
+    let device_override = params.effective_device_override();
+    let report = match PhysicalDevice::evaluate(instance.clone(), &params) {
+        Ok(report) => report,
+        Err(error) => return SelectionVerdict {
+            device_override: device_override.map(|device_override| device_override.to_string()),
+            selected: None,
+            error: Some(error.to_string()),
+            devices: vec![],
+        },
+    };
+
+    let selected = PhysicalDevice::select_from_report(&report, device_override.as_ref()).map(|device| device.index);
+    SelectionVerdict {
+        device_override: device_override.map(|device_override| device_override.to_string()),
+        selected,
+        error: selected.is_none().then(|| "no compatible device".to_string()),
+        devices: device_verdicts(&report),
+    }
+}
+
+fn device_verdicts(report: &SelectionReport) -> Vec<DeviceVerdict> {
+    report.devices.iter()
+        .map(|device| DeviceVerdict {
+            index: device.index,
+            name: device.name.clone(),
+            compatible: device.is_compatible(),
+            score: device.score,
+            rejections: device.rejections.iter().map(|reason| reason.to_string()).collect(),
+        })
+        .collect()
+}
+
+fn print_text(report: &Report, summary: bool) {
+    println!("Vulkan instance {} (loader {})", report.instance.api_version, report.instance.loader_version);
+    println!("Loader: {}", report.instance.loader);
+
+    println!();
+    println!("Layers ({}):", report.instance.layers.len());
+    for layer in &report.instance.layers {
+        println!("    {} {}: {}", layer.name, layer.version, layer.description);
+    }
+    println!("Instance extensions ({}):", report.instance.extensions.len());
+    for extension in &report.instance.extensions {
+        println!("    {} (revision {})", extension.name, extension.version);
+    }
+
+    for device in &report.devices {
+        println!();
+        print!("[{}] {}", device.index, device.info);
+        if summary {
+            continue;
+        }
+
+        println!("  limits:");
+        for (name, value) in &device.info.limits {
+            println!("    {} = {}", name, value);
+        }
+        println!("  features ({}):", device.info.features.len());
+        for feature in &device.info.features {
+            println!("    {}", feature);
+        }
+        println!("  extensions ({}):", device.info.extensions.len());
+        for extension in &device.info.extensions {
+            println!("    {}", extension);
+        }
+        println!("  formats ({}):", device.formats.len());
+        for format in &device.formats {
+            println!("    {}", format.format);
+            println!("        linear: {}", format.linear_tiling);
+            println!("        optimal: {}", format.optimal_tiling);
+            println!("        buffer: {}", format.buffer);
+        }
+    }
+
+    let selection = &report.selection;
+    println!();
+    println!("Selection:");
+    if let Some(device_override) = &selection.device_override {
+        println!("    pinned device: {}", device_override);
+    }
+    for device in &selection.devices {
+        match device.score {
+            Some(score) if device.compatible => println!("    [{}] {}: compatible, score {}", device.index, device.name, score),
+            _ => {
+                println!("    [{}] {}: rejected", device.index, device.name);
+                for reason in &device.rejections {
+                    println!("        - {}", reason);
+                }
+            }
+        }
+    }
+    match (selection.selected, &selection.error) {
+        (Some(index), _) => println!("Selected device: [{}]", index),
+        (None, Some(error)) => println!("No device selected: {}", error),
+        (None, None) => println!("No device selected"),
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let instance = unsafe { Instance::builder().application_name("graphicat-info").build()? };
+
+    let physical_devices = unsafe { instance.enumerate_physical_devices() }
+        .map_err(|result| graphicat::Error::Vulkan { call: "vkEnumeratePhysicalDevices", result })?;
+    let devices = physical_devices.into_iter()
+        .enumerate()
+        .map(|(index, physical_device)| {
+            let physical_device = PhysicalDevice::wrap(physical_device, instance.clone());
+            DeviceEntry {
+                index,
+                info: physical_device.info().clone(),
+                formats: format_report(&physical_device),
+            }
+        })
+        .collect();
+
+    let report = Report {
+        instance: instance_report(&instance),
+        devices,
+        selection: selection_verdict(&instance, &options),
+    };
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|error| format!("could not serialize the report: {}", error))?;
+        println!("{}", json);
+    } else {
+        print_text(&report, options.summary);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("graphicat-info: {}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("graphicat-info: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
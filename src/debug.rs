@@ -0,0 +1,261 @@
+use std::borrow::Cow;
+use std::ffi::{c_void, CStr, CString};
+use std::panic::AssertUnwindSafe;
+use ash::extensions::ext;
+use ash::prelude::VkResult;
+use ash::vk;
+use crate::error::{Error, Result};
+use crate::queue::Queue;
+
+pub const VALIDATION_LAYER_NAME: &str = "VK_LAYER_KHRONOS_validation";
+
+/// A message received from the debug utils messenger.
+#[derive(Debug, Clone)]
+pub struct DebugMessage<'a> {
+    pub severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    pub message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    pub message_id_name: Cow<'a, str>,
+    pub message_id_number: i32,
+    pub message: Cow<'a, str>,
+}
+
+pub type DebugCallback = Box<dyn Fn(&DebugMessage) + Send + Sync>;
+
+/// Validation and debug messenger configuration for instance creation.
+///
+/// The validation layer and `VK_EXT_debug_utils` are only enabled when available. Messages are sent to the callback if one is set, otherwise to the `log` facade with the `graphicat::vulkan` target.
+pub struct DebugSettings {
+    validation: bool,
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_types: vk::DebugUtilsMessageTypeFlagsEXT,
+    callback: Option<DebugCallback>,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self {
+            validation: true,
+            severity: vk::DebugUtilsMessageSeverityFlagsEXT::WARNING | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+            message_types: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+            callback: None,
+        }
+    }
+}
+
+impl DebugSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable `VK_LAYER_KHRONOS_validation` if it is installed.
+    pub fn validation(mut self, enabled: bool) -> Self {
+        self.validation = enabled;
+        self
+    }
+
+    /// Severities of the messages to receive.
+    pub fn severity(mut self, severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Types of the messages to receive.
+    pub fn message_types(mut self, message_types: vk::DebugUtilsMessageTypeFlagsEXT) -> Self {
+        self.message_types = message_types;
+        self
+    }
+
+    /// Send messages to `callback` instead of the `log` facade. Messages can be emitted from any thread using the instance.
+    pub fn callback<F: Fn(&DebugMessage) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn validation_enabled(&self) -> bool {
+        self.validation
+    }
+
+    /// Take the sink messages are sent to, its address is given to Vulkan as user data by `messenger_create_info`.
+    pub(crate) fn take_sink(&mut self) -> Box<DebugSink> {
+        Box::new(match self.callback.take() {
+            Some(callback) => DebugSink::Callback(callback),
+            None => DebugSink::Log,
+        })
+    }
+
+    /// The create info for a messenger sending messages to `sink`. `sink` must outlive the messenger.
+    pub(crate) fn messenger_create_info(&self, sink: &DebugSink) -> vk::DebugUtilsMessengerCreateInfoEXT {
+        vk::DebugUtilsMessengerCreateInfoEXT::builder()
+            .message_severity(self.severity)
+            .message_type(self.message_types)
+            .pfn_user_callback(Some(debug_messenger_callback))
+            .user_data(sink as *const DebugSink as *mut c_void)
+            .build()
+    }
+}
+
+/// Where messages go. Boxed by the messenger so that its address can be given to Vulkan as user data.
+pub(crate) enum DebugSink {
+    Log,
+    Callback(DebugCallback),
+}
+
+impl DebugSink {
+    fn send(&self, message: &DebugMessage) {
+        match self {
+            DebugSink::Log => {
+                let level = if message.severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+                    log::Level::Error
+                } else if message.severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+                    log::Level::Warn
+                } else if message.severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+                    log::Level::Info
+                } else {
+                    log::Level::Trace
+                };
+
+                log::log!(target: "graphicat::vulkan", level, "[{:?}] {}: {}", message.message_type, message.message_id_name, message.message);
+            }
+            DebugSink::Callback(callback) => callback(message),
+        }
+    }
+}
+
+unsafe extern "system" fn debug_messenger_callback(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut c_void,
+) -> vk::Bool32 {
+    if callback_data.is_null() || user_data.is_null() {
+        return vk::FALSE;
+    }
+
+    let callback_data = &*callback_data;
+    let to_str = |ptr: *const std::ffi::c_char| if ptr.is_null() { Cow::Borrowed("") } else { CStr::from_ptr(ptr).to_string_lossy() };
+
+    let message = DebugMessage {
+        severity,
+        message_type,
+        message_id_name: to_str(callback_data.p_message_id_name),
+        message_id_number: callback_data.message_id_number,
+        message: to_str(callback_data.p_message),
+    };
+
+    // Unwinding into the Vulkan loader is undefined behaviour, so a panicking callback is contained here.
+    let sink = &*(user_data as *const DebugSink);
+    let _ = std::panic::catch_unwind(AssertUnwindSafe(|| sink.send(&message)));
+
+    vk::FALSE
+}
+
+/// A debug utils messenger and the sink its messages are sent to.
+pub(crate) struct DebugMessenger {
+    debug_utils: ext::DebugUtils,
+    messenger: vk::DebugUtilsMessengerEXT,
+    _sink: Box<DebugSink>,
+}
+
+impl DebugMessenger {
+    pub(crate) unsafe fn new(entry: &ash::Entry, instance: &ash::Instance, settings: &DebugSettings, sink: Box<DebugSink>, allocation_callbacks: Option<&vk::AllocationCallbacks>) -> VkResult<Self> {
+        let debug_utils = ext::DebugUtils::new(entry, instance);
+        let messenger = debug_utils.create_debug_utils_messenger(&settings.messenger_create_info(&sink), allocation_callbacks)?;
+
+        Ok(Self {
+            debug_utils,
+            messenger,
+            _sink: sink,
+        })
+    }
+
+    /// Must be called before the instance is destroyed, with the allocation callbacks the messenger was created with.
+    pub(crate) unsafe fn destroy(&self, allocation_callbacks: Option<&vk::AllocationCallbacks>) {
+        self.debug_utils.destroy_debug_utils_messenger(self.messenger, allocation_callbacks);
+    }
+}
+
+/// Name `handle` for validation messages and debugging tools such as RenderDoc. Does nothing when `debug_utils` is `None`.
+pub(crate) fn set_object_name<H: vk::Handle>(debug_utils: Option<&ext::DebugUtils>, device: vk::Device, handle: H, name: &str) -> Result<()> {
+    let debug_utils = match debug_utils {
+        Some(debug_utils) => debug_utils,
+        None => return Ok(()),
+    };
+
+    let name = CString::new(name).unwrap_or_default();
+    let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+        .object_type(H::TYPE)
+        .object_handle(handle.as_raw())
+        .object_name(&name);
+    unsafe { debug_utils.set_debug_utils_object_name(device, &name_info) }.map_err(Error::vulkan("vkSetDebugUtilsObjectNameEXT"))
+}
+
+fn label(name: &CStr, color: [f32; 4]) -> vk::DebugUtilsLabelEXT {
+    vk::DebugUtilsLabelEXT::builder()
+        .label_name(name)
+        .color(color)
+        .build()
+}
+
+/// A labelled region of a command buffer, begun by `Device::command_buffer_label` and ended when dropped.
+pub struct CommandBufferLabel<'a> {
+    debug_utils: Option<&'a ext::DebugUtils>,
+    command_buffer: vk::CommandBuffer,
+}
+
+impl<'a> CommandBufferLabel<'a> {
+    pub(crate) unsafe fn begin(debug_utils: Option<&'a ext::DebugUtils>, command_buffer: vk::CommandBuffer, name: &str, color: [f32; 4]) -> Self {
+        if let Some(debug_utils) = debug_utils {
+            let name = CString::new(name).unwrap_or_default();
+            debug_utils.cmd_begin_debug_utils_label(command_buffer, &label(&name, color));
+        }
+
+        Self {
+            debug_utils,
+            command_buffer,
+        }
+    }
+}
+
+impl Drop for CommandBufferLabel<'_> {
+    fn drop(&mut self) {
+        if let Some(debug_utils) = self.debug_utils {
+            unsafe {
+                debug_utils.cmd_end_debug_utils_label(self.command_buffer);
+            }
+        }
+    }
+}
+
+/// A labelled region of a queue, begun by `Device::queue_label` and ended when dropped.
+///
+/// The queue is only locked while beginning and ending the region, so it can be used in between.
+pub struct QueueLabel<'a> {
+    debug_utils: Option<&'a ext::DebugUtils>,
+    queue: &'a Queue,
+}
+
+impl<'a> QueueLabel<'a> {
+    pub(crate) fn begin(debug_utils: Option<&'a ext::DebugUtils>, queue: &'a Queue, name: &str, color: [f32; 4]) -> Self {
+        if let Some(debug_utils) = debug_utils {
+            let name = CString::new(name).unwrap_or_default();
+            unsafe {
+                debug_utils.queue_begin_debug_utils_label(*queue.lock(), &label(&name, color));
+            }
+        }
+
+        Self {
+            debug_utils,
+            queue,
+        }
+    }
+}
+
+impl Drop for QueueLabel<'_> {
+    fn drop(&mut self) {
+        if let Some(debug_utils) = self.debug_utils {
+            unsafe {
+                debug_utils.queue_end_debug_utils_label(*self.queue.lock());
+            }
+        }
+    }
+}
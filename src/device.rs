@@ -0,0 +1,347 @@
+use std::ffi::{CStr, CString};
+use std::ops::Deref;
+use std::sync::Arc;
+use ash::vk;
+use crate::debug::{set_object_name, CommandBufferLabel, QueueLabel};
+use crate::error::{Error, Result};
+use crate::features::DeviceFeatures;
+use crate::ffi_util::CStringArray;
+use crate::gpu::{GpuSelectionParameters, PhysicalDevice, required_device_extensions};
+use crate::queue::{Queue, QueueFamilyIndices};
+use crate::surface::Surface;
+use crate::util::HandleOwnership;
+
+/// What to enable when creating a logical device.
+pub struct DeviceParameters<'a> {
+    extensions: Vec<&'a CStr>,
+    optional_extensions: Vec<&'a CStr>,
+    features: DeviceFeatures,
+    surface: Option<&'a Surface>,
+}
+
+impl Default for DeviceParameters<'_> {
+    fn default() -> Self {
+        Self {
+            extensions: required_device_extensions(),
+            optional_extensions: vec![],
+            features: DeviceFeatures::default(),
+            surface: None,
+        }
+    }
+}
+
+impl<'a> DeviceParameters<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parameters for devices that never present: like `default()` but without enabling `VK_KHR_swapchain`.
+    pub fn headless() -> Self {
+        Self {
+            extensions: vec![],
+            ..Self::default()
+        }
+    }
+
+    /// Enable the extensions and features required by the selection parameters, and resolve a present queue for their surface if there is one.
+    pub fn from_selection(selection_parameters: &GpuSelectionParameters<'a>) -> Self {
+        Self {
+            extensions: selection_parameters.required_extensions().to_vec(),
+            optional_extensions: vec![],
+            features: *selection_parameters.required_features(),
+            surface: selection_parameters.surface(),
+        }
+    }
+
+    pub fn enable_extension(mut self, extension: &'a CStr) -> Self {
+        if !self.extensions.contains(&extension) {
+            self.extensions.push(extension);
+        }
+        self
+    }
+
+    pub fn enable_extensions<I: IntoIterator<Item = &'a CStr>>(self, extensions: I) -> Self {
+        extensions.into_iter().fold(self, |params, extension| params.enable_extension(extension))
+    }
+
+    /// Enable an extension if the device supports it, without failing device creation otherwise. Check `Device::is_extension_enabled` to know whether it was enabled.
+    pub fn enable_optional_extension(mut self, extension: &'a CStr) -> Self {
+        if !self.optional_extensions.contains(&extension) {
+            self.optional_extensions.push(extension);
+        }
+        self
+    }
+
+    pub fn enable_optional_extensions<I: IntoIterator<Item = &'a CStr>>(self, extensions: I) -> Self {
+        extensions.into_iter().fold(self, |params, extension| params.enable_optional_extension(extension))
+    }
+
+    /// The extensions to enable on `physical_device`: the requested ones, which must all be supported, and the optional ones it supports.
+    fn resolve_extensions(&self, physical_device: &PhysicalDevice) -> Result<Vec<CString>> {
+        let supported_extensions = physical_device.supported_extensions()?;
+        let missing_extensions: Vec<String> = self.extensions.iter()
+            .filter(|extension| !supported_extensions.contains(**extension))
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .collect();
+        if !missing_extensions.is_empty() {
+            return Err(Error::MissingDeviceExtensions(missing_extensions));
+        }
+
+        let mut extensions: Vec<CString> = self.extensions.iter().map(|extension| (*extension).to_owned()).collect();
+        for extension in &self.optional_extensions {
+            if extensions.iter().any(|enabled| enabled.as_c_str() == *extension) {
+                continue;
+            }
+            if supported_extensions.contains(*extension) {
+                extensions.push((*extension).to_owned());
+            } else {
+                log::info!("optional device extension {} is not supported by {}", extension.to_string_lossy(), physical_device.name());
+            }
+        }
+        Ok(extensions)
+    }
+
+    /// Enable every feature enabled in `features`, in addition to the features already enabled.
+    pub fn enable_features(mut self, features: DeviceFeatures) -> Self {
+        self.features = self.features.union(&features);
+        self
+    }
+
+    /// Create a queue able to present to the surface.
+    pub fn present_to(mut self, surface: &'a Surface) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+}
+
+/// A logical device. One queue is created for every distinct queue family resolved for graphics, compute, transfer and presentation.
+pub struct Device {
+    physical_device: Arc<PhysicalDevice>,
+    device: ash::Device,
+    queue_families: QueueFamilyIndices,
+    queues: Vec<Queue>,
+    enabled_extensions: Vec<CString>,
+    enabled_features: DeviceFeatures,
+    ownership: HandleOwnership,
+}
+
+impl Device {
+    pub fn new(physical_device: Arc<PhysicalDevice>, parameters: &DeviceParameters) -> Result<Arc<Self>> {
+        let queue_families = physical_device.resolve_queue_families(parameters.surface)?;
+        if parameters.surface.is_some() && queue_families.present.is_none() {
+            return Err(Error::NoPresentQueue);
+        }
+
+        let missing_features = parameters.features.missing_from(&physical_device.features());
+        if !missing_features.is_empty() {
+            return Err(Error::MissingDeviceFeatures(missing_features));
+        }
+        let enabled_extensions = parameters.resolve_extensions(&physical_device)?;
+
+        let queue_priorities = [1.0f32];
+        let queue_create_infos: Vec<vk::DeviceQueueCreateInfo> = queue_families.unique().into_iter()
+            .map(|family_index| vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(family_index)
+                .queue_priorities(&queue_priorities)
+                .build())
+            .collect();
+
+        let extensions_cstr_array = CStringArray::from_vec(&enabled_extensions);
+
+        // The feature structures are only valid from the version they were introduced in, Vulkan 1.0 only has `p_enabled_features`.
+        let api_version = physical_device.api_version();
+        let mut features = parameters.features;
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder().features(features.vulkan_1_0);
+        let mut create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_extension_names(extensions_cstr_array.as_ptr_slice());
+        if api_version >= vk::API_VERSION_1_1 {
+            create_info = create_info.push_next(&mut features2);
+        } else {
+            create_info = create_info.enabled_features(&features.vulkan_1_0);
+        }
+        if api_version >= vk::API_VERSION_1_2 {
+            create_info = create_info.push_next(&mut features.vulkan_1_1).push_next(&mut features.vulkan_1_2);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            create_info = create_info.push_next(&mut features.vulkan_1_3);
+        }
+
+        let device = unsafe {
+            physical_device.instance().create_device(physical_device.handle(), &create_info, physical_device.instance().allocation_callbacks())
+        }.map_err(Error::vulkan("vkCreateDevice"))?;
+
+        let queues = queue_families.unique().into_iter()
+            .map(|family_index| Queue::new(unsafe { device.get_device_queue(family_index, 0) }, family_index, 0))
+            .collect();
+
+        Ok(Arc::new(Self {
+            physical_device,
+            device,
+            queue_families,
+            queues,
+            enabled_extensions,
+            enabled_features: parameters.features,
+            ownership: HandleOwnership::Owned,
+        }))
+    }
+
+    /// Wrap a device created elsewhere from `physical_device`.
+    ///
+    /// # Safety
+    /// The first queue of every family in `queue_families` must have been created with the device. Extensions and features the device was created with are not known, so `enabled_extensions` and `enabled_features` report none.
+    /// With `HandleOwnership::Owned` the device is destroyed when the wrapper is dropped.
+    pub unsafe fn from_raw(physical_device: Arc<PhysicalDevice>, device: ash::Device, queue_families: QueueFamilyIndices, ownership: HandleOwnership) -> Arc<Self> {
+        let queues = queue_families.unique().into_iter()
+            .map(|family_index| Queue::new(device.get_device_queue(family_index, 0), family_index, 0))
+            .collect();
+
+        Arc::new(Self {
+            physical_device,
+            device,
+            queue_families,
+            queues,
+            enabled_extensions: vec![],
+            enabled_features: DeviceFeatures::default(),
+            ownership,
+        })
+    }
+
+    pub fn handle(&self) -> &ash::Device {
+        &self.device
+    }
+
+    pub fn physical_device(&self) -> &Arc<PhysicalDevice> {
+        &self.physical_device
+    }
+
+    pub fn queue_families(&self) -> QueueFamilyIndices {
+        self.queue_families
+    }
+
+    /// Every created queue, one per distinct queue family.
+    pub fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    fn queue_for_family(&self, family_index: Option<u32>) -> Option<&Queue> {
+        self.queues.iter().find(|queue| Some(queue.family_index()) == family_index)
+    }
+
+    pub fn graphics_queue(&self) -> Option<&Queue> {
+        self.queue_for_family(self.queue_families.graphics)
+    }
+
+    pub fn compute_queue(&self) -> Option<&Queue> {
+        self.queue_for_family(self.queue_families.compute)
+    }
+
+    pub fn transfer_queue(&self) -> Option<&Queue> {
+        self.queue_for_family(self.queue_families.transfer)
+    }
+
+    pub fn present_queue(&self) -> Option<&Queue> {
+        self.queue_for_family(self.queue_families.present)
+    }
+
+    pub fn enabled_extensions(&self) -> &[CString] {
+        &self.enabled_extensions
+    }
+
+    /// Whether the device was created with `extension`, either requested or optional.
+    pub fn is_extension_enabled(&self, extension: &CStr) -> bool {
+        self.enabled_extensions.iter().any(|enabled| enabled.as_c_str() == extension)
+    }
+
+    pub fn enabled_features(&self) -> &DeviceFeatures {
+        &self.enabled_features
+    }
+
+    /// Name the device for validation messages and debugging tools. Does nothing without `VK_EXT_debug_utils`.
+    pub fn set_debug_name(&self, name: &str) -> Result<()> {
+        set_object_name(self.physical_device.instance().debug_utils(), self.device.handle(), self.device.handle(), name)
+    }
+
+    /// Name any object created from this device, e.g. a buffer or a pipeline. Does nothing without `VK_EXT_debug_utils`.
+    ///
+    /// # Safety
+    /// `handle` must be a valid object created from this device, and no other thread may use it during the call.
+    pub unsafe fn set_object_name<H: vk::Handle>(&self, handle: H, name: &str) -> Result<()> {
+        set_object_name(self.physical_device.instance().debug_utils(), self.device.handle(), handle, name)
+    }
+
+    /// Begin a labelled region of `command_buffer`, ended when the returned guard is dropped. Does nothing without `VK_EXT_debug_utils`.
+    ///
+    /// # Safety
+    /// `command_buffer` must have been allocated from this device and be recording, both when calling this function and when dropping the guard.
+    pub unsafe fn command_buffer_label(&self, command_buffer: vk::CommandBuffer, name: &str, color: [f32; 4]) -> CommandBufferLabel<'_> {
+        CommandBufferLabel::begin(self.physical_device.instance().debug_utils(), command_buffer, name, color)
+    }
+
+    /// Begin a labelled region of `queue`, ended when the returned guard is dropped. Does nothing without `VK_EXT_debug_utils`.
+    pub fn queue_label<'a>(&'a self, queue: &'a Queue, name: &str, color: [f32; 4]) -> QueueLabel<'a> {
+        QueueLabel::begin(self.physical_device.instance().debug_utils(), queue, name, color)
+    }
+
+    /// The allocation callbacks of the instance, to create and destroy objects of this device with.
+    pub fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks> {
+        self.physical_device.instance().allocation_callbacks()
+    }
+}
+
+impl Deref for Device {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        if self.ownership == HandleOwnership::Imported {
+            return;
+        }
+
+        unsafe {
+            // Nothing can be done about a failure here, the device is destroyed either way.
+            let _ = self.device.device_wait_idle();
+            self.device.destroy_device(self.allocation_callbacks());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::extensions::{ext, khr};
+    use ash::vk;
+    use crate::backend::fake::{physical_device, FakeDevice};
+    use crate::error::Error;
+    use super::DeviceParameters;
+
+    #[test]
+    fn optional_extensions_are_enabled_when_supported() {
+        let device = FakeDevice::new("device", vk::PhysicalDeviceType::DISCRETE_GPU).extension(ext::MeshShader::name());
+        let physical_device = physical_device(device);
+
+        let parameters = DeviceParameters::new()
+            .enable_optional_extension(ext::MeshShader::name())
+            .enable_optional_extension(vk::ExtMemoryBudgetFn::name())
+            .enable_optional_extension(khr::Swapchain::name());
+        let extensions = parameters.resolve_extensions(&physical_device).unwrap();
+        assert_eq!(extensions, [khr::Swapchain::name().to_owned(), ext::MeshShader::name().to_owned()]);
+    }
+
+    #[test]
+    fn unsupported_requested_extensions_are_reported() {
+        let device = FakeDevice::new("device", vk::PhysicalDeviceType::DISCRETE_GPU).without_extension(khr::Swapchain::name());
+        let physical_device = physical_device(device);
+
+        let parameters = DeviceParameters::new().enable_extension(ext::MeshShader::name());
+        match parameters.resolve_extensions(&physical_device) {
+            Err(Error::MissingDeviceExtensions(extensions)) => assert_eq!(extensions, ["VK_KHR_swapchain", "VK_EXT_mesh_shader"]),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(DeviceParameters::headless().resolve_extensions(&physical_device).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::error::Error;
+use crate::gpu::PhysicalDevice;
+use crate::util::format_uuid;
+
+/// Environment variable read by `DeviceOverride::from_env`, using the syntax of `DeviceOverride::from_str`.
+pub const DEVICE_OVERRIDE_ENV: &str = "GRAPHICAT_DEVICE";
+
+/// Pins device selection to one device, see `GpuSelectionParameters::device_override`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceOverride {
+    /// Index of the device in enumeration order, as shown in the selection report.
+    Index(usize),
+    /// Case insensitive substring of the device name.
+    Name(String),
+    /// The device UUID, only available on Vulkan 1.1 devices.
+    Uuid([u8; 16]),
+    /// PCI vendor and device IDs.
+    Id {
+        vendor_id: u32,
+        device_id: u32,
+    },
+}
+
+impl DeviceOverride {
+    /// The override set with `GRAPHICAT_DEVICE`, if any. An invalid value is reported with a warning and ignored.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var(DEVICE_OVERRIDE_ENV).ok()?;
+        if value.trim().is_empty() {
+            return None;
+        }
+
+        match value.parse() {
+            Ok(device_override) => Some(device_override),
+            Err(error) => {
+                log::warn!("ignoring {}: {}", DEVICE_OVERRIDE_ENV, error);
+                None
+            }
+        }
+    }
+
+    /// Whether the device enumerated at `index` is the pinned device.
+    pub fn matches(&self, index: usize, physical_device: &PhysicalDevice) -> bool {
+        match self {
+            DeviceOverride::Index(pinned) => *pinned == index,
+            DeviceOverride::Name(name) => physical_device.name().to_lowercase().contains(&name.to_lowercase()),
+            DeviceOverride::Uuid(uuid) => physical_device.uuid() == Some(*uuid),
+            DeviceOverride::Id { vendor_id, device_id } => {
+                let properties = physical_device.properties();
+                properties.vendor_id == *vendor_id && properties.device_id == *device_id
+            }
+        }
+    }
+}
+
+fn invalid(value: &str, reason: &str) -> Error {
+    Error::InvalidDeviceOverride {
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_hex(value: &str) -> Option<u32> {
+    let value = value.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(value, 16).ok()
+}
+
+fn parse_uuid(value: &str) -> Option<[u8; 16]> {
+    let digits: String = value.chars().filter(|c| *c != '-').collect();
+    if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut uuid = [0; 16];
+    for (i, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(uuid)
+}
+
+fn parse_id(value: &str) -> Option<(u32, u32)> {
+    let (vendor_id, device_id) = value.split_once(':')?;
+    Some((parse_hex(vendor_id)?, parse_hex(device_id)?))
+}
+
+/// Parses `index:N`, `name:TEXT`, `uuid:UUID` or `id:VENDOR:DEVICE` with hexadecimal IDs, e.g. `id:10de:2684`.
+///
+/// Without a prefix, a number is an index, a UUID formatted as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` is a UUID, two hexadecimal numbers separated by `:` are IDs and anything else is a name.
+impl FromStr for DeviceOverride {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (kind, argument) = match value.split_once(':') {
+            Some((kind, argument)) if ["index", "name", "uuid", "id"].contains(&kind) => (Some(kind), argument),
+            _ => (None, value),
+        };
+
+        match kind {
+            Some("index") => argument.parse().map(DeviceOverride::Index).map_err(|_| invalid(value, "the index is not a number")),
+            Some("name") if argument.is_empty() => Err(invalid(value, "the name is empty")),
+            Some("name") => Ok(DeviceOverride::Name(argument.to_string())),
+            Some("uuid") => parse_uuid(argument).map(DeviceOverride::Uuid).ok_or_else(|| invalid(value, "the UUID is not 32 hexadecimal digits")),
+            Some(_) => parse_id(argument)
+                .map(|(vendor_id, device_id)| DeviceOverride::Id { vendor_id, device_id })
+                .ok_or_else(|| invalid(value, "the IDs are not formatted as VENDOR:DEVICE in hexadecimal")),
+            None => {
+                if let Ok(index) = argument.parse() {
+                    Ok(DeviceOverride::Index(index))
+                } else if let Some(uuid) = parse_uuid(argument).filter(|_| argument.len() == 36) {
+                    Ok(DeviceOverride::Uuid(uuid))
+                } else if let Some((vendor_id, device_id)) = parse_id(argument) {
+                    Ok(DeviceOverride::Id { vendor_id, device_id })
+                } else if argument.is_empty() {
+                    Err(invalid(value, "the value is empty"))
+                } else {
+                    Ok(DeviceOverride::Name(argument.to_string()))
+                }
+            }
+        }
+    }
+}
+
+impl Display for DeviceOverride {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceOverride::Index(index) => write!(f, "index:{}", index),
+            DeviceOverride::Name(name) => write!(f, "name:{}", name),
+            DeviceOverride::Uuid(uuid) => write!(f, "uuid:{}", format_uuid(uuid)),
+            DeviceOverride::Id { vendor_id, device_id } => write!(f, "id:{:04x}:{:04x}", vendor_id, device_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use super::DeviceOverride;
+
+    #[test]
+    fn parses_index() {
+        assert_eq!("1".parse::<DeviceOverride>().unwrap(), DeviceOverride::Index(1));
+        assert_eq!("index:2".parse::<DeviceOverride>().unwrap(), DeviceOverride::Index(2));
+    }
+
+    #[test]
+    fn parses_uuid() {
+        let uuid = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+        assert_eq!("01234567-89ab-cdef-0123-456789abcdef".parse::<DeviceOverride>().unwrap(), DeviceOverride::Uuid(uuid));
+        assert_eq!("uuid:0123456789ABCDEF0123456789ABCDEF".parse::<DeviceOverride>().unwrap(), DeviceOverride::Uuid(uuid));
+    }
+
+    #[test]
+    fn parses_ids() {
+        let id = DeviceOverride::Id { vendor_id: 0x10de, device_id: 0x2684 };
+        assert_eq!("10de:2684".parse::<DeviceOverride>().unwrap(), id);
+        assert_eq!("id:0x10de:0x2684".parse::<DeviceOverride>().unwrap(), id);
+    }
+
+    #[test]
+    fn parses_name() {
+        assert_eq!("GeForce RTX".parse::<DeviceOverride>().unwrap(), DeviceOverride::Name("GeForce RTX".to_string()));
+        assert_eq!("name:1".parse::<DeviceOverride>().unwrap(), DeviceOverride::Name("1".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for value in ["", "index:first", "name:", "uuid:0123", "id:10de", "id:nvidia:2684"] {
+            assert!(matches!(value.parse::<DeviceOverride>(), Err(Error::InvalidDeviceOverride { .. })), "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for device_override in [DeviceOverride::Index(3), DeviceOverride::Name("radeon".to_string()), DeviceOverride::Uuid([7; 16]), DeviceOverride::Id { vendor_id: 0x1002, device_id: 0x744c }] {
+            assert_eq!(device_override.to_string().parse::<DeviceOverride>().unwrap(), device_override);
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+/// Environment variables read by the Vulkan loader that change which drivers and layers are found.
+pub const LOADER_ENVIRONMENT_VARIABLES: &[&str] = &[
+    "VK_ICD_FILENAMES",
+    "VK_DRIVER_FILES",
+    "VK_ADD_DRIVER_FILES",
+    "VK_LOADER_DRIVERS_SELECT",
+    "VK_LOADER_DRIVERS_DISABLE",
+    "VK_LAYER_PATH",
+    "VK_ADD_LAYER_PATH",
+    "VK_INSTANCE_LAYERS",
+    "VK_LOADER_LAYERS_ENABLE",
+    "VK_LOADER_LAYERS_DISABLE",
+    "VK_LOADER_DEBUG",
+    "LD_LIBRARY_PATH",
+    "DYLD_LIBRARY_PATH",
+];
+
+/// File name of the loader library opened by `ash::Entry::load`.
+#[cfg(target_os = "windows")]
+pub const LOADER_LIBRARY_NAME: &str = "vulkan-1.dll";
+#[cfg(target_os = "macos")]
+pub const LOADER_LIBRARY_NAME: &str = "libvulkan.dylib";
+#[cfg(target_os = "android")]
+pub const LOADER_LIBRARY_NAME: &str = "libvulkan.so";
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "android")))]
+pub const LOADER_LIBRARY_NAME: &str = "libvulkan.so.1";
+
+// Directories searched by the dynamic linker when LD_LIBRARY_PATH does not contain the library.
+const SYSTEM_LIBRARY_DIRECTORIES: &[&str] = &[
+    "/usr/local/lib",
+    "/usr/local/lib64",
+    "/usr/lib",
+    "/usr/lib64",
+    "/lib",
+    "/lib64",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/usr/lib/i386-linux-gnu",
+    "/usr/lib/arm-linux-gnueabihf",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestKind {
+    Driver,
+    ExplicitLayer,
+    ImplicitLayer,
+}
+
+impl ManifestKind {
+    fn directory(self) -> &'static str {
+        match self {
+            ManifestKind::Driver => "icd.d",
+            ManifestKind::ExplicitLayer => "explicit_layer.d",
+            ManifestKind::ImplicitLayer => "implicit_layer.d",
+        }
+    }
+}
+
+/// Whether the library a manifest refers to can be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryStatus {
+    Found(PathBuf),
+    /// The path in the manifest does not exist.
+    Missing(PathBuf),
+    /// A bare file name not found in the usual library directories, the dynamic linker may still find it through its cache.
+    NotInSearchPath(String),
+}
+
+/// A driver (ICD) or layer manifest file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub kind: ManifestKind,
+    /// Layer name, `None` for drivers.
+    pub name: Option<String>,
+    pub api_version: Option<String>,
+    pub library: Option<LibraryStatus>,
+    /// Why the manifest could not be read or parsed.
+    pub error: Option<String>,
+}
+
+impl Manifest {
+    fn read(path: &Path, kind: ManifestKind) -> Vec<Manifest> {
+        let failed = |error: String| vec![Manifest {
+            path: path.to_path_buf(),
+            kind,
+            name: None,
+            api_version: None,
+            library: None,
+            error: Some(error),
+        }];
+
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => return failed(error.to_string()),
+        };
+        let json: Value = match serde_json::from_str(&content) {
+            Ok(json) => json,
+            Err(error) => return failed(format!("invalid JSON: {}", error)),
+        };
+
+        // Layer manifests contain either one `layer` or a `layers` array.
+        let entries: Vec<&Value> = match kind {
+            ManifestKind::Driver => json.get("ICD").into_iter().collect(),
+            _ => match (json.get("layer"), json.get("layers").and_then(Value::as_array)) {
+                (Some(layer), _) => vec![layer],
+                (None, Some(layers)) => layers.iter().collect(),
+                (None, None) => vec![],
+            },
+        };
+        if entries.is_empty() {
+            return failed(format!("no {} entry", if kind == ManifestKind::Driver { "ICD" } else { "layer" }));
+        }
+
+        entries.into_iter()
+            .map(|entry| {
+                let field = |name: &str| entry.get(name).and_then(Value::as_str).map(str::to_string);
+                let library_path = field("library_path");
+                Manifest {
+                    path: path.to_path_buf(),
+                    kind,
+                    name: field("name"),
+                    api_version: field("api_version"),
+                    error: match (&library_path, kind) {
+                        // Meta layers only reference other layers and have no library.
+                        (None, ManifestKind::Driver) => Some("no library_path".to_string()),
+                        _ => None,
+                    },
+                    library: library_path.map(|library_path| resolve_library(path, &library_path)),
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.error.is_none() && !matches!(self.library, Some(LibraryStatus::Missing(_)))
+    }
+}
+
+impl Display for Manifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        if let Some(api_version) = &self.api_version {
+            write!(f, " (Vulkan {})", api_version)?;
+        }
+        if let Some(error) = &self.error {
+            return write!(f, ": {}", error);
+        }
+        match &self.library {
+            Some(LibraryStatus::Found(library)) => write!(f, ": {}", library.display()),
+            Some(LibraryStatus::Missing(library)) => write!(f, ": library {} does not exist", library.display()),
+            Some(LibraryStatus::NotInSearchPath(library)) => write!(f, ": {} not found in the library directories, it may still be found by the dynamic linker", library),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Library paths in manifests are absolute, relative to the manifest if they contain a separator, or else a file name for the dynamic linker.
+fn resolve_library(manifest: &Path, library_path: &str) -> LibraryStatus {
+    let library = Path::new(library_path);
+    if library.is_absolute() || library_path.contains('/') || library_path.contains('\\') {
+        let library = manifest.parent().map(|directory| directory.join(library)).unwrap_or_else(|| library.to_path_buf());
+        return if library.exists() { LibraryStatus::Found(library) } else { LibraryStatus::Missing(library) };
+    }
+
+    match find_library(library_path) {
+        Some(library) => LibraryStatus::Found(library),
+        None => LibraryStatus::NotInSearchPath(library_path.to_string()),
+    }
+}
+
+fn library_directories() -> Vec<PathBuf> {
+    let mut directories = vec![];
+    for variable in ["LD_LIBRARY_PATH", "DYLD_LIBRARY_PATH"] {
+        if let Some(paths) = std::env::var_os(variable) {
+            directories.extend(std::env::split_paths(&paths));
+        }
+    }
+    directories.extend(SYSTEM_LIBRARY_DIRECTORIES.iter().map(PathBuf::from));
+    directories
+}
+
+fn find_library(name: &str) -> Option<PathBuf> {
+    library_directories().into_iter()
+        .map(|directory| directory.join(name))
+        .find(|library| library.exists())
+}
+
+fn env_paths(variable: &str) -> Vec<PathBuf> {
+    std::env::var_os(variable)
+        .map(|paths| std::env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// The directories the loader searches for manifests of a kind, following the XDG base directories like the loader does.
+///
+/// Empty on Windows, where the loader finds manifests through the registry instead.
+fn manifest_directories(kind: ManifestKind) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        return vec![];
+    }
+
+    let home = std::env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from);
+    let xdg = |variable: &str, home_default: &str, system_default: &str| -> Vec<PathBuf> {
+        let mut directories = env_paths(variable);
+        if directories.is_empty() {
+            directories = match &home {
+                Some(home) if !home_default.is_empty() => vec![home.join(home_default)],
+                _ if system_default.is_empty() => vec![],
+                _ => std::env::split_paths(system_default).collect(),
+            };
+        }
+        directories
+    };
+
+    let mut roots = xdg("XDG_CONFIG_HOME", ".config", "");
+    roots.extend(xdg("XDG_CONFIG_DIRS", "", "/etc/xdg"));
+    roots.push(PathBuf::from("/etc"));
+    roots.extend(xdg("XDG_DATA_HOME", ".local/share", ""));
+    roots.extend(xdg("XDG_DATA_DIRS", "", "/usr/local/share:/usr/share"));
+
+    roots.into_iter().map(|root| root.join("vulkan").join(kind.directory())).collect()
+}
+
+/// The manifest files of a directory or file list entry, sorted by name like the loader does.
+fn manifest_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path).into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// What the Vulkan loader would find on this machine: the loader library, driver and layer manifests and the environment variables affecting them.
+///
+/// Collected by `Error::Loading` and `Error::NoDriver` to explain why no Vulkan implementation could be used.
+/// On Windows the registry is not searched, so only manifests given by the environment variables are listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// Where the loader library was found, `None` if it is not in the library directories.
+    pub loader_library: Option<PathBuf>,
+    /// The loader environment variables that are set.
+    pub environment: Vec<(String, String)>,
+    pub drivers: Vec<Manifest>,
+    pub explicit_layers: Vec<Manifest>,
+    pub implicit_layers: Vec<Manifest>,
+    /// Readable descriptions of everything that prevents Vulkan from working.
+    pub problems: Vec<String>,
+}
+
+impl Diagnostics {
+    pub fn collect() -> Self {
+        let environment: Vec<(String, String)> = LOADER_ENVIRONMENT_VARIABLES.iter()
+            .filter_map(|variable| Some((variable.to_string(), std::env::var(variable).ok()?)))
+            .collect();
+        let mut problems = vec![];
+
+        let loader_library = find_library(LOADER_LIBRARY_NAME);
+        if loader_library.is_none() && cfg!(all(unix, not(target_os = "macos"))) {
+            problems.push(format!("{} was not found in the library directories, install the Vulkan loader (e.g. libvulkan1 or vulkan-loader)", LOADER_LIBRARY_NAME));
+        }
+
+        // VK_DRIVER_FILES replaces VK_ICD_FILENAMES, both replace the search of the driver directories.
+        let driver_variable = ["VK_DRIVER_FILES", "VK_ICD_FILENAMES"].into_iter().find(|variable| !env_paths(variable).is_empty());
+        let mut driver_paths = driver_variable.map(env_paths).unwrap_or_default();
+        let driver_override = driver_variable.is_some();
+        for path in &driver_paths {
+            if !path.exists() {
+                problems.push(format!("{} contains {}, which does not exist", driver_variable.unwrap_or_default(), path.display()));
+            }
+        }
+        if !driver_override {
+            driver_paths = manifest_directories(ManifestKind::Driver);
+        }
+        driver_paths.extend(env_paths("VK_ADD_DRIVER_FILES"));
+        let drivers = Self::read_manifests(&driver_paths, ManifestKind::Driver);
+
+        let mut explicit_layer_paths = env_paths("VK_LAYER_PATH");
+        if explicit_layer_paths.is_empty() {
+            explicit_layer_paths = manifest_directories(ManifestKind::ExplicitLayer);
+        }
+        explicit_layer_paths.extend(env_paths("VK_ADD_LAYER_PATH"));
+        let explicit_layers = Self::read_manifests(&explicit_layer_paths, ManifestKind::ExplicitLayer);
+        let implicit_layers = Self::read_manifests(&manifest_directories(ManifestKind::ImplicitLayer), ManifestKind::ImplicitLayer);
+
+        problems.extend(Self::driver_problem(&drivers, driver_override));
+        for manifest in drivers.iter().chain(&explicit_layers).chain(&implicit_layers) {
+            if !manifest.is_usable() {
+                problems.push(manifest.to_string());
+            }
+        }
+
+        Self {
+            loader_library,
+            environment,
+            drivers,
+            explicit_layers,
+            implicit_layers,
+            problems,
+        }
+    }
+
+    /// What is wrong with the driver manifests as a whole, if anything.
+    fn driver_problem(drivers: &[Manifest], driver_override: bool) -> Option<String> {
+        if drivers.is_empty() {
+            if driver_override {
+                Some("no driver manifest was found in the files given by the environment".to_string())
+            } else if cfg!(not(windows)) {
+                Some("no driver manifest was found, install a Vulkan driver (e.g. mesa-vulkan-drivers, or the GPU vendor driver)".to_string())
+            } else {
+                // The registry, where Windows drivers are registered, is not searched.
+                None
+            }
+        } else if !drivers.iter().any(Manifest::is_usable) {
+            Some("no driver manifest refers to an existing library".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn read_manifests(paths: &[PathBuf], kind: ManifestKind) -> Vec<Manifest> {
+        paths.iter()
+            .flat_map(|path| manifest_files(path))
+            .filter(|file| file.exists())
+            .flat_map(|file| Manifest::read(&file, kind))
+            .collect()
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Vulkan environment:")?;
+        match &self.loader_library {
+            Some(loader_library) => writeln!(f, "  loader: {}", loader_library.display())?,
+            None => writeln!(f, "  loader: {} not found in the library directories", LOADER_LIBRARY_NAME)?,
+        }
+
+        for (variable, value) in &self.environment {
+            writeln!(f, "  {}={}", variable, value)?;
+        }
+
+        for (title, manifests) in [("drivers", &self.drivers), ("explicit layers", &self.explicit_layers), ("implicit layers", &self.implicit_layers)] {
+            writeln!(f, "  {} ({}):", title, manifests.len())?;
+            for manifest in manifests {
+                writeln!(f, "    {}", manifest)?;
+            }
+        }
+
+        if !self.problems.is_empty() {
+            writeln!(f, "  problems:")?;
+            for problem in &self.problems {
+                writeln!(f, "    - {}", problem)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use super::{resolve_library, Diagnostics, LibraryStatus, Manifest, ManifestKind};
+
+    /// A directory of its own for each test, removed when dropped.
+    struct TempDirectory(PathBuf);
+
+    impl TempDirectory {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("graphicat-diagnostics-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDirectory {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_driver_manifest() {
+        let directory = TempDirectory::new("driver");
+        directory.write("libvulkan_fake.so", "");
+        let path = directory.write("fake_icd.json", r#"{"file_format_version": "1.0.0", "ICD": {"library_path": "./libvulkan_fake.so", "api_version": "1.3.250"}}"#);
+
+        let manifests = Manifest::read(&path, ManifestKind::Driver);
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0].api_version.as_deref(), Some("1.3.250"));
+        assert_eq!(manifests[0].library, Some(LibraryStatus::Found(directory.0.join("./libvulkan_fake.so"))));
+        assert!(manifests[0].is_usable());
+    }
+
+    #[test]
+    fn driver_without_library_is_unusable() {
+        let directory = TempDirectory::new("driver-without-library");
+        let path = directory.write("fake_icd.json", r#"{"ICD": {"api_version": "1.3.250"}}"#);
+
+        let manifests = Manifest::read(&path, ManifestKind::Driver);
+        assert_eq!(manifests[0].error.as_deref(), Some("no library_path"));
+        assert!(!manifests[0].is_usable());
+    }
+
+    #[test]
+    fn reads_layer_manifests() {
+        let directory = TempDirectory::new("layers");
+        let layer = directory.write("layer.json", r#"{"layer": {"name": "VK_LAYER_fake", "library_path": "./missing.so"}}"#);
+        let layers = directory.write("layers.json", r#"{"layers": [
+            {"name": "VK_LAYER_first", "library_path": "/nonexistent/libfirst.so"},
+            {"name": "VK_LAYER_meta", "component_layers": ["VK_LAYER_first"]}
+        ]}"#);
+
+        let manifests = Manifest::read(&layer, ManifestKind::ExplicitLayer);
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0].name.as_deref(), Some("VK_LAYER_fake"));
+        assert_eq!(manifests[0].library, Some(LibraryStatus::Missing(directory.0.join("./missing.so"))));
+        assert!(!manifests[0].is_usable());
+
+        let manifests = Manifest::read(&layers, ManifestKind::ImplicitLayer);
+        let names: Vec<_> = manifests.iter().map(|manifest| manifest.name.as_deref()).collect();
+        assert_eq!(names, [Some("VK_LAYER_first"), Some("VK_LAYER_meta")]);
+        assert_eq!(manifests[1].library, None);
+        assert!(manifests[1].is_usable());
+    }
+
+    #[test]
+    fn reports_invalid_manifests() {
+        let directory = TempDirectory::new("invalid");
+        let invalid = directory.write("invalid.json", "{ not json");
+        let empty = directory.write("empty.json", "{}");
+
+        let manifests = Manifest::read(&invalid, ManifestKind::Driver);
+        assert_eq!(manifests.len(), 1);
+        assert!(manifests[0].error.as_deref().is_some_and(|error| error.starts_with("invalid JSON")));
+
+        assert_eq!(Manifest::read(&empty, ManifestKind::ExplicitLayer)[0].error.as_deref(), Some("no layer entry"));
+        assert!(Manifest::read(&directory.0.join("absent.json"), ManifestKind::Driver)[0].error.is_some());
+    }
+
+    #[test]
+    fn resolves_library_paths() {
+        let directory = TempDirectory::new("libraries");
+        let manifest = directory.0.join("icd.json");
+        let library = directory.write("libfake.so", "");
+
+        assert_eq!(resolve_library(&manifest, "./libfake.so"), LibraryStatus::Found(directory.0.join("./libfake.so")));
+        assert_eq!(resolve_library(&manifest, "lib/libfake.so"), LibraryStatus::Missing(directory.0.join("lib/libfake.so")));
+        assert_eq!(resolve_library(&manifest, library.to_str().unwrap()), LibraryStatus::Found(library.clone()));
+        assert_eq!(resolve_library(Path::new("icd.json"), "libgraphicat_nonexistent.so"), LibraryStatus::NotInSearchPath("libgraphicat_nonexistent.so".to_string()));
+    }
+
+    #[test]
+    fn no_driver_manifest_is_not_a_missing_library() {
+        for driver_override in [false, true] {
+            let problem = Diagnostics::driver_problem(&[], driver_override);
+            assert_ne!(problem.as_deref(), Some("no driver manifest refers to an existing library"));
+            assert_eq!(problem.is_some(), driver_override || cfg!(not(windows)));
+        }
+    }
+
+    #[test]
+    fn reports_drivers_without_library() {
+        let directory = TempDirectory::new("drivers-without-library");
+        let path = directory.write("fake_icd.json", r#"{"ICD": {"library_path": "./missing.so"}}"#);
+
+        let drivers = Manifest::read(&path, ManifestKind::Driver);
+        assert_eq!(Diagnostics::driver_problem(&drivers, false).as_deref(), Some("no driver manifest refers to an existing library"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use ash::vk;
+use crate::diagnostics::Diagnostics;
+use crate::report::SelectionReport;
+
+/// The error type of every fallible graphicat operation.
+#[derive(Debug)]
+pub enum Error {
+    /// The Vulkan library could not be loaded, `loader` describes which library was tried and `diagnostics` what the loader would have found.
+    Loading {
+        loader: String,
+        error: ash::LoadingError,
+        diagnostics: Box<Diagnostics>,
+    },
+    /// The loader found no usable driver, instance creation failed with `VK_ERROR_INCOMPATIBLE_DRIVER`.
+    NoDriver {
+        diagnostics: Box<Diagnostics>,
+    },
+    /// A driver given to `InstanceBuilder::driver` could not be loaded.
+    DriverLoading {
+        path: PathBuf,
+        reason: String,
+    },
+    /// A Vulkan call returned an error.
+    Vulkan {
+        call: &'static str,
+        result: vk::Result,
+    },
+    /// There is no surface support for the window system in use.
+    UnsupportedPlatform {
+        platform: String,
+    },
+    /// A window or display handle could not be used to create a surface.
+    InvalidWindowHandle {
+        platform: String,
+        reason: String,
+    },
+    MissingInstanceExtensions(Vec<String>),
+    MissingLayers(Vec<String>),
+    MissingDeviceFeatures(Vec<String>),
+    MissingDeviceExtensions(Vec<String>),
+    /// A present queue was requested but no queue family of the device can present to the surface.
+    NoPresentQueue,
+    /// No queue family of the device supports graphics.
+    NoGraphicsQueue,
+    /// A device override could not be parsed.
+    InvalidDeviceOverride {
+        value: String,
+        reason: String,
+    },
+    /// A Vulkan Profiles JSON file could not be read or parsed, `path` is `None` when it was given as a string.
+    InvalidProfile {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+    /// A profile file does not define the requested profile.
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    /// No device was compatible with the selection parameters, the report explains why each device was rejected.
+    NoCompatibleDevice(SelectionReport),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn vulkan(call: &'static str) -> impl FnOnce(vk::Result) -> Error {
+        move |result| Error::Vulkan {
+            call,
+            result,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Loading { loader, error, diagnostics } => write!(f, "failed to load the Vulkan library ({}): {}\n{}", loader, error, diagnostics.to_string().trim_end()),
+            Error::NoDriver { diagnostics } => write!(f, "no Vulkan driver is available\n{}", diagnostics.to_string().trim_end()),
+            Error::DriverLoading { path, reason } => write!(f, "failed to load the Vulkan driver {}: {}", path.display(), reason),
+            Error::Vulkan { call, result } => write!(f, "{} failed: {}", call, result),
+            Error::UnsupportedPlatform { platform } => write!(f, "surfaces are not supported on {}", platform),
+            Error::InvalidWindowHandle { platform, reason } => write!(f, "invalid {} window handle: {}", platform, reason),
+            Error::MissingInstanceExtensions(extensions) => write!(f, "instance extensions not available: {}", extensions.join(", ")),
+            Error::MissingLayers(layers) => write!(f, "instance layers not available: {}", layers.join(", ")),
+            Error::MissingDeviceFeatures(features) => write!(f, "device features not supported: {}", features.join(", ")),
+            Error::MissingDeviceExtensions(extensions) => write!(f, "device extensions not supported: {}", extensions.join(", ")),
+            Error::NoPresentQueue => write!(f, "no queue family can present to the surface"),
+            Error::NoGraphicsQueue => write!(f, "no queue family supports graphics"),
+            Error::InvalidDeviceOverride { value, reason } => write!(f, "invalid device override {:?}: {}", value, reason),
+            Error::InvalidProfile { path: Some(path), reason } => write!(f, "invalid Vulkan profile file {}: {}", path.display(), reason),
+            Error::InvalidProfile { path: None, reason } => write!(f, "invalid Vulkan profile: {}", reason),
+            Error::UnknownProfile { name, available } => write!(f, "no profile named {} (available: {})", name, available.join(", ")),
+            Error::NoCompatibleDevice(report) => write!(f, "no compatible GPU found\n{}", report),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Loading { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt::{Debug, Formatter};
+use ash::vk;
+use crate::instance::Instance;
+
+/// Device features from the Vulkan 1.0, 1.1, 1.2 and 1.3 feature structures.
+///
+/// The same value is used to describe the features a device supports, the features selection requires and the features enabled at device creation.
+/// Features are set through the public structures, e.g. `features.vulkan_1_3.dynamic_rendering = vk::TRUE`, or by name with `set`.
+#[derive(Clone, Copy, Default)]
+pub struct DeviceFeatures {
+    pub vulkan_1_0: vk::PhysicalDeviceFeatures,
+    pub vulkan_1_1: vk::PhysicalDeviceVulkan11Features,
+    pub vulkan_1_2: vk::PhysicalDeviceVulkan12Features,
+    pub vulkan_1_3: vk::PhysicalDeviceVulkan13Features,
+}
+
+// The `p_next` pointers of the structures are only used while chaining them for a Vulkan call, which overwrites them, and are never followed otherwise.
+unsafe impl Send for DeviceFeatures {}
+unsafe impl Sync for DeviceFeatures {}
+
+// Generates, for one feature structure, a module listing its features by name and giving mutable access to a feature from its name.
+macro_rules! feature_table {
+    ($module:ident, $ty:ty, [$($field:ident),* $(,)?]) => {
+        mod $module {
+            use ash::vk;
+
+            pub fn list(features: &$ty) -> Vec<(&'static str, bool)> {
+                vec![$((stringify!($field), features.$field == vk::TRUE)),*]
+            }
+
+            pub fn get_mut<'a>(features: &'a mut $ty, name: &str) -> Option<&'a mut vk::Bool32> {
+                match name {
+                    $(stringify!($field) => Some(&mut features.$field),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+feature_table!(vulkan_1_0, vk::PhysicalDeviceFeatures, [
+    robust_buffer_access, full_draw_index_uint32, image_cube_array, independent_blend, geometry_shader,
+    tessellation_shader, sample_rate_shading, dual_src_blend, logic_op, multi_draw_indirect,
+    draw_indirect_first_instance, depth_clamp, depth_bias_clamp, fill_mode_non_solid, depth_bounds,
+    wide_lines, large_points, alpha_to_one, multi_viewport, sampler_anisotropy, texture_compression_etc2,
+    texture_compression_astc_ldr, texture_compression_bc, occlusion_query_precise,
+    pipeline_statistics_query, vertex_pipeline_stores_and_atomics, fragment_stores_and_atomics,
+    shader_tessellation_and_geometry_point_size, shader_image_gather_extended,
+    shader_storage_image_extended_formats, shader_storage_image_multisample,
+    shader_storage_image_read_without_format, shader_storage_image_write_without_format,
+    shader_uniform_buffer_array_dynamic_indexing, shader_sampled_image_array_dynamic_indexing,
+    shader_storage_buffer_array_dynamic_indexing, shader_storage_image_array_dynamic_indexing,
+    shader_clip_distance, shader_cull_distance, shader_float64, shader_int64, shader_int16,
+    shader_resource_residency, shader_resource_min_lod, sparse_binding, sparse_residency_buffer,
+    sparse_residency_image2_d, sparse_residency_image3_d, sparse_residency2_samples,
+    sparse_residency4_samples, sparse_residency8_samples, sparse_residency16_samples,
+    sparse_residency_aliased, variable_multisample_rate, inherited_queries
+]);
+
+feature_table!(vulkan_1_1, vk::PhysicalDeviceVulkan11Features, [
+    storage_buffer16_bit_access, uniform_and_storage_buffer16_bit_access, storage_push_constant16,
+    storage_input_output16, multiview, multiview_geometry_shader, multiview_tessellation_shader,
+    variable_pointers_storage_buffer, variable_pointers, protected_memory, sampler_ycbcr_conversion,
+    shader_draw_parameters
+]);
+
+feature_table!(vulkan_1_2, vk::PhysicalDeviceVulkan12Features, [
+    sampler_mirror_clamp_to_edge, draw_indirect_count, storage_buffer8_bit_access,
+    uniform_and_storage_buffer8_bit_access, storage_push_constant8, shader_buffer_int64_atomics,
+    shader_shared_int64_atomics, shader_float16, shader_int8, descriptor_indexing,
+    shader_input_attachment_array_dynamic_indexing, shader_uniform_texel_buffer_array_dynamic_indexing,
+    shader_storage_texel_buffer_array_dynamic_indexing, shader_uniform_buffer_array_non_uniform_indexing,
+    shader_sampled_image_array_non_uniform_indexing, shader_storage_buffer_array_non_uniform_indexing,
+    shader_storage_image_array_non_uniform_indexing, shader_input_attachment_array_non_uniform_indexing,
+    shader_uniform_texel_buffer_array_non_uniform_indexing,
+    shader_storage_texel_buffer_array_non_uniform_indexing,
+    descriptor_binding_uniform_buffer_update_after_bind,
+    descriptor_binding_sampled_image_update_after_bind,
+    descriptor_binding_storage_image_update_after_bind,
+    descriptor_binding_storage_buffer_update_after_bind,
+    descriptor_binding_uniform_texel_buffer_update_after_bind,
+    descriptor_binding_storage_texel_buffer_update_after_bind,
+    descriptor_binding_update_unused_while_pending, descriptor_binding_partially_bound,
+    descriptor_binding_variable_descriptor_count, runtime_descriptor_array, sampler_filter_minmax,
+    scalar_block_layout, imageless_framebuffer, uniform_buffer_standard_layout,
+    shader_subgroup_extended_types, separate_depth_stencil_layouts, host_query_reset, timeline_semaphore,
+    buffer_device_address, buffer_device_address_capture_replay, buffer_device_address_multi_device,
+    vulkan_memory_model, vulkan_memory_model_device_scope,
+    vulkan_memory_model_availability_visibility_chains, shader_output_viewport_index,
+    shader_output_layer, subgroup_broadcast_dynamic_id
+]);
+
+feature_table!(vulkan_1_3, vk::PhysicalDeviceVulkan13Features, [
+    robust_image_access, inline_uniform_block, descriptor_binding_inline_uniform_block_update_after_bind,
+    pipeline_creation_cache_control, private_data, shader_demote_to_helper_invocation,
+    shader_terminate_invocation, subgroup_size_control, compute_full_subgroups, synchronization2,
+    texture_compression_astc_hdr, shader_zero_initialize_workgroup_memory, dynamic_rendering,
+    shader_integer_dot_product, maintenance4
+]);
+
+impl DeviceFeatures {
+    /// Every feature as `(group, name, enabled)`, the group being `vulkan_1_0`, `vulkan_1_1`, `vulkan_1_2` or `vulkan_1_3`.
+    pub fn list(&self) -> Vec<(&'static str, &'static str, bool)> {
+        let groups = [
+            ("vulkan_1_0", vulkan_1_0::list(&self.vulkan_1_0)),
+            ("vulkan_1_1", vulkan_1_1::list(&self.vulkan_1_1)),
+            ("vulkan_1_2", vulkan_1_2::list(&self.vulkan_1_2)),
+            ("vulkan_1_3", vulkan_1_3::list(&self.vulkan_1_3)),
+        ];
+
+        groups.into_iter()
+            .flat_map(|(group, features)| features.into_iter().map(move |(name, enabled)| (group, name, enabled)))
+            .collect()
+    }
+
+    /// Names of the enabled features, formatted as `group.name`.
+    pub fn enabled_names(&self) -> Vec<String> {
+        self.list().into_iter()
+            .filter(|(_, _, enabled)| *enabled)
+            .map(|(group, name, _)| format!("{}.{}", group, name))
+            .collect()
+    }
+
+    /// Names of the features enabled in `self` but not in `available`, formatted as `group.name`.
+    pub fn missing_from(&self, available: &DeviceFeatures) -> Vec<String> {
+        self.list().into_iter()
+            .zip(available.list())
+            .filter(|((_, _, required), (_, _, supported))| *required && !*supported)
+            .map(|((group, name, _), _)| format!("{}.{}", group, name))
+            .collect()
+    }
+
+    /// Mutable access to a feature from its group (`vulkan_1_0` to `vulkan_1_3`) and its snake case name.
+    pub fn get_mut(&mut self, group: &str, name: &str) -> Option<&mut vk::Bool32> {
+        match group {
+            "vulkan_1_0" => vulkan_1_0::get_mut(&mut self.vulkan_1_0, name),
+            "vulkan_1_1" => vulkan_1_1::get_mut(&mut self.vulkan_1_1, name),
+            "vulkan_1_2" => vulkan_1_2::get_mut(&mut self.vulkan_1_2, name),
+            "vulkan_1_3" => vulkan_1_3::get_mut(&mut self.vulkan_1_3, name),
+            _ => None,
+        }
+    }
+
+    /// Set a feature by name, either `group.name` or just `name` in which case the first group containing it is used. Returns false if no such feature exists.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let value = if enabled { vk::TRUE } else { vk::FALSE };
+        let feature = match name.split_once('.') {
+            Some((group, name)) => self.get_mut(group, name),
+            None => {
+                let group = ["vulkan_1_0", "vulkan_1_1", "vulkan_1_2", "vulkan_1_3"].into_iter()
+                    .find(|group| self.get_mut(group, name).is_some());
+                match group {
+                    Some(group) => self.get_mut(group, name),
+                    None => None,
+                }
+            }
+        };
+
+        match feature {
+            Some(feature) => {
+                *feature = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enable every feature enabled in either `self` or `other`.
+    pub fn union(mut self, other: &DeviceFeatures) -> Self {
+        for (group, name, enabled) in other.list() {
+            if enabled {
+                if let Some(feature) = self.get_mut(group, name) {
+                    *feature = vk::TRUE;
+                }
+            }
+        }
+        self
+    }
+
+    /// Query the features supported by a physical device, `api_version` being the version usable with the device.
+    ///
+    /// The 1.1 and 1.2 structures are only queried from Vulkan 1.2, and the 1.3 structure from Vulkan 1.3, the other structures are left empty.
+    pub fn query(instance: &Instance, physical_device: vk::PhysicalDevice, api_version: u32) -> Self {
+        instance.backend().features(physical_device, api_version)
+    }
+
+    /// Reset the `p_next` pointers of the structures so that they can be chained again.
+    pub(crate) fn clear_chain(&mut self) {
+        self.vulkan_1_1.p_next = std::ptr::null_mut();
+        self.vulkan_1_2.p_next = std::ptr::null_mut();
+        self.vulkan_1_3.p_next = std::ptr::null_mut();
+    }
+}
+
+impl Debug for DeviceFeatures {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.enabled_names()).finish()
+    }
+}
+
+impl PartialEq for DeviceFeatures {
+    fn eq(&self, other: &Self) -> bool {
+        self.list() == other.list()
+    }
+}
+
+impl Eq for DeviceFeatures {}
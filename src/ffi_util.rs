@@ -6,12 +6,12 @@ pub struct CStringArray {
 }
 
 impl CStringArray {
-    pub fn from_vec<T: Into<Vec<u8>> + Clone>(strings: &Vec<T>) -> Self {
+    pub fn from_vec<T: Into<Vec<u8>> + Clone>(strings: &[T]) -> Self {
         let cstrings: Vec<CString> = strings.iter().cloned()
             .map(|s| CString::new(s).unwrap_or_default())
             .collect();
 
-        let cstrings_ptrs: Vec<*const c_char> = (&cstrings).iter()
+        let cstrings_ptrs: Vec<*const c_char> = cstrings.iter()
             .map(|cs| cs.as_ptr())
             .collect();
 
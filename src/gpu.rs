@@ -1,10 +1,24 @@
 use std::collections::HashSet;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use ash::prelude::VkResult;
 use ash::{vk, extensions::*};
+use crate::backend::ExtendedProperties;
+use crate::debug::set_object_name;
+use crate::device::Device;
+use crate::device_override::DeviceOverride;
+use crate::features::DeviceFeatures;
+use crate::info::{c_string, DeviceInfo};
 use crate::instance::Instance;
+use crate::limits::LimitRequirements;
+use crate::profiles::Profile;
+use crate::error::{Error, Result};
+use crate::report::{DeviceReport, RejectionReason, SelectionReport};
+use crate::surface::Surface;
+use crate::scoring::{DeviceTypeScorer, GpuScorer, VramScorer};
 
+/// Device extensions required to present, enabled by default during selection and device creation.
 pub fn required_device_extensions() -> Vec<&'static CStr> {
     vec![
         khr::Swapchain::name(),
@@ -13,18 +27,52 @@ pub fn required_device_extensions() -> Vec<&'static CStr> {
 
 #[derive(Clone)]
 pub struct PhysicalDevice {
-    instance: Rc<Instance>,
+    instance: Arc<Instance>,
     physical_device: vk::PhysicalDevice,
+    // Queried from the driver on first use, as selection and profile checks read them for every device.
+    properties: OnceLock<vk::PhysicalDeviceProperties>,
+    memory_properties: OnceLock<vk::PhysicalDeviceMemoryProperties>,
+    features: OnceLock<DeviceFeatures>,
+    extensions: OnceLock<VkResult<HashSet<CString>>>,
+    extended_properties: OnceLock<ExtendedProperties>,
+    info: OnceLock<DeviceInfo>,
 }
 
+/// A user supplied check run against every device that passed the built-in selection criteria.
 pub trait GpuCompatibilityChecker {
     fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool;
+
+    /// Name shown in the selection report when this checker rejects a device.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+impl<F: Fn(&PhysicalDevice) -> bool> GpuCompatibilityChecker for F {
+    fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool {
+        self(physical_device)
+    }
 }
 
 pub struct GpuSelectionParameters<'a> {
     allowed_types: Vec<vk::PhysicalDeviceType>,
     required_extension_support: Vec<&'a CStr>,
-    user_compatibility_checker: Option<Box<dyn GpuCompatibilityChecker>>
+    required_features: DeviceFeatures,
+    required_limits: LimitRequirements,
+    min_api_version: u32,
+    surface: Option<&'a Surface>,
+    profiles: Vec<Profile>,
+    compatibility_checkers: Vec<Box<dyn GpuCompatibilityChecker + 'a>>,
+    scorers: Vec<Box<dyn GpuScorer + 'a>>,
+    device_override: Option<DeviceOverride>,
+    env_override: bool,
+}
+
+/// A compatible device together with the score it was given during selection.
+#[derive(Clone)]
+pub struct RankedDevice {
+    pub device: Arc<PhysicalDevice>,
+    pub score: i64,
 }
 
 impl Default for GpuSelectionParameters<'_> {
@@ -32,100 +80,437 @@ impl Default for GpuSelectionParameters<'_> {
         Self {
             allowed_types: vec![vk::PhysicalDeviceType::DISCRETE_GPU, vk::PhysicalDeviceType::INTEGRATED_GPU, vk::PhysicalDeviceType::CPU, vk::PhysicalDeviceType::VIRTUAL_GPU, vk::PhysicalDeviceType::OTHER],
             required_extension_support: required_device_extensions(),
-            user_compatibility_checker: None,
+            required_features: DeviceFeatures::default(),
+            required_limits: LimitRequirements::default(),
+            min_api_version: vk::API_VERSION_1_0,
+            surface: None,
+            profiles: vec![],
+            compatibility_checkers: vec![],
+            scorers: vec![Box::new(DeviceTypeScorer::default()), Box::new(VramScorer)],
+            device_override: None,
+            env_override: true,
         }
     }
 }
 
 impl <'a> GpuSelectionParameters<'a> {
-    unsafe fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool {
-        if !self.allowed_types.contains(&physical_device.device_type()) { false }
-        else {
-            match physical_device.instance.enumerate_device_extension_properties(physical_device.physical_device) {
-                Ok(extensions) => {
-                    let mut available_extension_names = HashSet::<&CStr>::new();
-                    for extension in &extensions {
-                        available_extension_names.insert(CStr::from_ptr(extension.extension_name.as_ptr()));
-                    }
-
-                    for extension in &self.required_extension_support {
-                        if !available_extension_names.contains(extension) { return false }
-                    }
-
-                    if let Some(checker) = &self.user_compatibility_checker {
-                        checker.is_compatible(physical_device)
-                    } else {
-                        true
-                    }
+    /// Parameters for devices that never present: like `default()` but without requiring `VK_KHR_swapchain`.
+    pub fn headless() -> Self {
+        Self {
+            required_extension_support: vec![],
+            ..Self::default()
+        }
+    }
+
+    /// Same as `default()`: every device type is allowed, `required_device_extensions()` must be supported and devices are scored by type then by VRAM size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow devices of the given type to be selected.
+    pub fn allow_type(mut self, device_type: vk::PhysicalDeviceType) -> Self {
+        if !self.allowed_types.contains(&device_type) {
+            self.allowed_types.push(device_type);
+        }
+        self
+    }
+
+    /// Prevent devices of the given type from being selected.
+    pub fn deny_type(mut self, device_type: vk::PhysicalDeviceType) -> Self {
+        self.allowed_types.retain(|t| *t != device_type);
+        self
+    }
+
+    /// Replace the set of allowed device types.
+    pub fn only_types(mut self, device_types: &[vk::PhysicalDeviceType]) -> Self {
+        self.allowed_types = device_types.to_vec();
+        self
+    }
+
+    /// Require the device to support an extension, in addition to the ones already required.
+    pub fn require_extension(mut self, extension: &'a CStr) -> Self {
+        if !self.required_extension_support.contains(&extension) {
+            self.required_extension_support.push(extension);
+        }
+        self
+    }
+
+    pub fn require_extensions<I: IntoIterator<Item = &'a CStr>>(self, extensions: I) -> Self {
+        extensions.into_iter().fold(self, |params, extension| params.require_extension(extension))
+    }
+
+    /// Require the device to support every feature enabled in `features`, in addition to the features already required.
+    pub fn require_features(mut self, features: DeviceFeatures) -> Self {
+        self.required_features = self.required_features.union(&features);
+        self
+    }
+
+    /// Require the device limits to reach the given values, in addition to the limits already required.
+    pub fn require_limits(mut self, limits: LimitRequirements) -> Self {
+        self.required_limits = self.required_limits.merge(&limits);
+        self
+    }
+
+    /// Require the Vulkan version usable with the device, see `PhysicalDevice::api_version`, to be at least the given version created with `vk::make_api_version`.
+    pub fn min_api_version(mut self, version: u32) -> Self {
+        self.min_api_version = version;
+        self
+    }
+
+    /// Require the device to be able to present to the surface: at least one queue family has to support presentation, and the surface has to report at least one format and one present mode.
+    pub fn present_to(mut self, surface: &'a Surface) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+
+    /// Require every capability of a Vulkan profile. Its extensions and features are not enabled by `DeviceParameters::from_selection`, see `Profile::extensions` and `Profile::features`.
+    pub fn require_profile(mut self, profile: Profile) -> Self {
+        self.profiles.push(profile);
+        self
+    }
+
+    /// Add a compatibility checker. Every checker has to accept a device for it to be selected.
+    pub fn add_checker<C: GpuCompatibilityChecker + 'a>(mut self, checker: C) -> Self {
+        self.compatibility_checkers.push(Box::new(checker));
+        self
+    }
+
+    /// Add a scorer. Its score is added to the score of the scorers already present.
+    pub fn add_scorer<S: GpuScorer + 'a>(mut self, scorer: S) -> Self {
+        self.scorers.push(Box::new(scorer));
+        self
+    }
+
+    /// Remove every scorer, including the default ones, so that only scorers added afterwards are used.
+    pub fn clear_scorers(mut self) -> Self {
+        self.scorers.clear();
+        self
+    }
+
+    /// Select the given device if it is compatible, instead of the device with the highest score. Takes precedence over `GRAPHICAT_DEVICE`.
+    pub fn device_override(mut self, device_override: DeviceOverride) -> Self {
+        self.device_override = Some(device_override);
+        self
+    }
+
+    /// Whether the `GRAPHICAT_DEVICE` environment variable is used when no override is set with `device_override`. Enabled by default.
+    pub fn env_override(mut self, enabled: bool) -> Self {
+        self.env_override = enabled;
+        self
+    }
+
+    pub fn allowed_types(&self) -> &[vk::PhysicalDeviceType] {
+        &self.allowed_types
+    }
+
+    pub fn required_extensions(&self) -> &[&'a CStr] {
+        &self.required_extension_support
+    }
+
+    /// The features a device has to support, to be enabled when creating the logical device.
+    pub fn required_features(&self) -> &DeviceFeatures {
+        &self.required_features
+    }
+
+    pub fn required_limits(&self) -> &LimitRequirements {
+        &self.required_limits
+    }
+
+    pub fn required_api_version(&self) -> u32 {
+        self.min_api_version
+    }
+
+    pub fn required_profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub fn surface(&self) -> Option<&'a Surface> {
+        self.surface
+    }
+
+    pub fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        self.scorers.iter().map(|scorer| scorer.score(physical_device)).sum()
+    }
+
+    /// Every reason for which the device does not satisfy these parameters. An empty list means the device is compatible.
+    pub fn rejections(&self, physical_device: &PhysicalDevice) -> Vec<RejectionReason> {
+        let mut rejections = vec![];
+
+        let device_type = physical_device.device_type();
+        if !self.allowed_types.contains(&device_type) {
+            rejections.push(RejectionReason::DisallowedType(device_type));
+        }
+
+        let properties = physical_device.properties();
+        let api_version = physical_device.api_version();
+        if api_version < self.min_api_version {
+            rejections.push(RejectionReason::ApiVersionTooLow {
+                required: self.min_api_version,
+                actual: api_version,
+            });
+        }
+
+        rejections.extend(self.required_limits.unmet(&properties.limits).into_iter().map(RejectionReason::LimitNotMet));
+
+        match physical_device.supported_extensions() {
+            Ok(available_extension_names) => {
+                let missing_extensions: Vec<String> = self.required_extension_support.iter()
+                    .filter(|extension| !available_extension_names.contains(**extension))
+                    .map(|extension| extension.to_string_lossy().into_owned())
+                    .collect();
+
+                if !missing_extensions.is_empty() {
+                    rejections.push(RejectionReason::MissingExtensions(missing_extensions));
                 }
-                Err(_) => false
             }
+            Err(error) => rejections.push(error.into()),
+        }
+
+        let missing_features = self.required_features.missing_from(&physical_device.features());
+        if !missing_features.is_empty() {
+            rejections.push(RejectionReason::MissingFeatures(missing_features));
+        }
+
+        for profile in &self.profiles {
+            let missing = profile.missing(physical_device);
+            if !missing.is_empty() {
+                rejections.push(RejectionReason::ProfileNotMet {
+                    profile: profile.name().to_string(),
+                    missing,
+                });
+            }
+        }
+
+        if let Some(surface) = self.surface {
+            rejections.extend(Self::presentation_rejections(physical_device, surface));
+        }
+
+        // Checkers are only consulted for devices that passed the built-in criteria.
+        if rejections.is_empty() {
+            for checker in &self.compatibility_checkers {
+                if !checker.is_compatible(physical_device) {
+                    rejections.push(RejectionReason::CheckerVeto(checker.name()));
+                }
+            }
+        }
+
+        rejections
+    }
+
+    fn presentation_rejections(physical_device: &PhysicalDevice, surface: &Surface) -> Vec<RejectionReason> {
+        let mut rejections = vec![];
+
+        let mut can_present = false;
+        for family in physical_device.queue_families() {
+            match surface.supports_present(physical_device, family.index) {
+                Ok(supported) => can_present |= supported,
+                Err(error) => return vec![error.into()],
+            }
+        }
+        if !can_present {
+            rejections.push(RejectionReason::NoPresentQueue);
+        }
+
+        match surface.formats(physical_device) {
+            Ok(formats) if formats.is_empty() => rejections.push(RejectionReason::NoSurfaceFormats),
+            Ok(_) => {}
+            Err(error) => rejections.push(error.into()),
+        }
+
+        match surface.present_modes(physical_device) {
+            Ok(present_modes) if present_modes.is_empty() => rejections.push(RejectionReason::NoPresentModes),
+            Ok(_) => {}
+            Err(error) => rejections.push(error.into()),
+        }
+
+        rejections
+    }
+
+    pub fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool {
+        self.rejections(physical_device).is_empty()
+    }
+
+    /// The override used by `PhysicalDevice::select`: the one set with `device_override`, or else the one of `GRAPHICAT_DEVICE` if enabled.
+    pub fn effective_device_override(&self) -> Option<DeviceOverride> {
+        match &self.device_override {
+            Some(device_override) => Some(device_override.clone()),
+            None if self.env_override => DeviceOverride::from_env(),
+            None => None,
         }
     }
 }
 
 impl PhysicalDevice {
-    pub fn select(instance: Rc<Instance>, selection_parameters: GpuSelectionParameters) -> Option<Rc<Self>> {
-        let mut discrete_device: Option<PhysicalDevice> = None; // this will remain None if the selection parameters dont allow for discrete gpus
-        let mut integrated_device: Option<PhysicalDevice> = None;
-        let mut supported_device: Option<PhysicalDevice> = None;
-
-        unsafe {
-            match instance.enumerate_physical_devices() {
-                Ok(physical_devices) => {
-                    for physical_device_v in physical_devices {
-                        let physical_device = PhysicalDevice::wrap(physical_device_v, instance.clone());
-
-                        if selection_parameters.is_compatible(&physical_device) {
-                            // No need to replicate the value across the lower tiers as they will be ignored at the end once the better type of gpu is found (no need for integrated_device once a compatible discrete_device is found).
-                            if physical_device.device_type() == vk::PhysicalDeviceType::DISCRETE_GPU {
-                                discrete_device = Some(physical_device);
-                            } else if physical_device.device_type() == vk::PhysicalDeviceType::INTEGRATED_GPU {
-                                integrated_device = Some(physical_device);
-                            } else {
-                                supported_device = Some(physical_device);
-                            }
-                        }
-                    }
-                }
-                Err(_) => return None
-            }
+    /// Select the compatible device with the highest score. When scores are equal the device enumerated first wins.
+    /// If no device is compatible the error contains a report explaining why each device was rejected.
+    ///
+    /// A device pinned with `GpuSelectionParameters::device_override` or `GRAPHICAT_DEVICE` is selected instead if it is compatible, the first compatible one when several match (e.g. by name), otherwise a warning is logged and the device is selected by score.
+    pub fn select(instance: Arc<Instance>, selection_parameters: GpuSelectionParameters) -> Result<Arc<Self>> {
+        let report = Self::evaluate(instance, &selection_parameters)?;
+        let device_override = selection_parameters.effective_device_override();
+
+        match Self::select_from_report(&report, device_override.as_ref()) {
+            Some(device) => Ok(device.device.clone()),
+            None => Err(Error::NoCompatibleDevice(report)),
         }
+    }
 
+    /// The device `select` picks from a report returned by `evaluate`, `None` if no device is compatible.
+    ///
+    /// Pass `GpuSelectionParameters::effective_device_override` as `device_override` to select like `select` does.
+    pub fn select_from_report<'a>(report: &'a SelectionReport, device_override: Option<&DeviceOverride>) -> Option<&'a DeviceReport> {
+        if let Some(device_override) = device_override {
+            let pinned: Vec<_> = report.devices.iter().filter(|device| device_override.matches(device.index, &device.device)).collect();
+            if let Some(device) = pinned.iter().find(|device| device.is_compatible()) {
+                return Some(device);
+            }
 
-        if let Some(gpu) = discrete_device {
-            Some(Rc::new(gpu))
-        } else if let Some(gpu) = integrated_device {
-            Some(Rc::new(gpu))
-        } else if let Some(gpu) = supported_device {
-            Some(Rc::new(gpu))
-        } else {
-            None
+            match pinned.first() {
+                Some(device) => log::warn!("no device pinned by {} is compatible, selecting by score instead\n{}", device_override, device),
+                None => log::warn!("no device matches {}, selecting by score instead", device_override),
+            }
         }
+
+        // `min_by_key` keeps the first of equal elements, so the device enumerated first wins ties.
+        report.compatible()
+            .filter(|device| device.score.is_some())
+            .min_by_key(|device| std::cmp::Reverse(device.score))
+    }
+
+    /// Every compatible device, best first.
+    pub fn select_all(instance: Arc<Instance>, selection_parameters: &GpuSelectionParameters) -> Result<Vec<Arc<Self>>> {
+        Ok(Self::rank(instance, selection_parameters)?.into_iter().map(|ranked| ranked.device).collect())
     }
 
-    pub fn wrap(physical_device: vk::PhysicalDevice, instance: Rc<Instance>) -> PhysicalDevice {
+    /// Every compatible device with its score, sorted from the highest to the lowest score. The sort is stable so devices with equal scores keep their enumeration order.
+    pub fn rank(instance: Arc<Instance>, selection_parameters: &GpuSelectionParameters) -> Result<Vec<RankedDevice>> {
+        Ok(Self::rank_report(&Self::evaluate(instance, selection_parameters)?))
+    }
+
+    /// Check every device against the selection parameters without selecting one.
+    pub fn evaluate(instance: Arc<Instance>, selection_parameters: &GpuSelectionParameters) -> Result<SelectionReport> {
+        let physical_devices = instance.backend().physical_devices()
+            .map_err(Error::vulkan("vkEnumeratePhysicalDevices"))?;
+
+        let devices = physical_devices.into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| {
+                let physical_device = PhysicalDevice::wrap(physical_device, instance.clone());
+                let rejections = selection_parameters.rejections(&physical_device);
+
+                DeviceReport {
+                    index,
+                    name: physical_device.name(),
+                    device_type: physical_device.device_type(),
+                    score: if rejections.is_empty() { Some(selection_parameters.score(&physical_device)) } else { None },
+                    rejections,
+                    device: Arc::new(physical_device),
+                }
+            })
+            .collect();
+
+        Ok(SelectionReport {
+            devices,
+        })
+    }
+
+    fn rank_report(report: &SelectionReport) -> Vec<RankedDevice> {
+        let mut ranked: Vec<RankedDevice> = report.compatible()
+            .filter_map(|device| Some(RankedDevice {
+                score: device.score?,
+                device: device.device.clone(),
+            }))
+            .collect();
+
+        ranked.sort_by_key(|ranked| std::cmp::Reverse(ranked.score));
+        ranked
+    }
+
+    pub fn wrap(physical_device: vk::PhysicalDevice, instance: Arc<Instance>) -> PhysicalDevice {
         Self {
             instance,
             physical_device,
+            properties: OnceLock::new(),
+            memory_properties: OnceLock::new(),
+            features: OnceLock::new(),
+            extensions: OnceLock::new(),
+            extended_properties: OnceLock::new(),
+            info: OnceLock::new(),
         }
     }
 
+    /// The Vulkan version usable with this device: the lower of the version supported by the device and the version of the instance.
+    pub fn api_version(&self) -> u32 {
+        self.supported_api_version().min(self.instance.api_version())
+    }
+
+    /// The Vulkan version supported by the device driver, which may be higher than the version of the instance.
+    pub fn supported_api_version(&self) -> u32 {
+        self.properties().api_version
+    }
+
     pub fn device_type(&self) -> vk::PhysicalDeviceType {
-        unsafe {
-            self.instance.get_physical_device_properties(self.physical_device).device_type
-        }
+        self.properties().device_type
+    }
+
+    /// Everything the device reports, built on first use from the cached queries and cached.
+    pub fn info(&self) -> &DeviceInfo {
+        self.info.get_or_init(|| DeviceInfo::query(self))
+    }
+
+    pub fn properties(&self) -> vk::PhysicalDeviceProperties {
+        *self.properties.get_or_init(|| self.instance.backend().properties(self.physical_device))
+    }
+
+    /// The properties only available through `vkGetPhysicalDeviceProperties2`, with the PCI bus properties when `VK_EXT_pci_bus_info` is supported.
+    pub(crate) fn extended_properties(&self) -> &ExtendedProperties {
+        self.extended_properties.get_or_init(|| {
+            let api_version = self.api_version();
+            let has_pci_bus_info = api_version >= vk::API_VERSION_1_1
+                && self.supported_extensions().is_ok_and(|extensions| extensions.contains(vk::ExtPciBusInfoFn::name()));
+            self.instance.backend().extended_properties(self.physical_device, api_version, has_pci_bus_info)
+        })
+    }
+
+    pub fn memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        *self.memory_properties.get_or_init(|| self.instance.backend().memory_properties(self.physical_device))
+    }
+
+    /// The device UUID, `None` before Vulkan 1.1.
+    pub fn uuid(&self) -> Option<[u8; 16]> {
+        self.extended_properties().id.map(|id| id.device_uuid)
+    }
+
+    pub fn features(&self) -> DeviceFeatures {
+        *self.features.get_or_init(|| DeviceFeatures::query(&self.instance, self.physical_device, self.api_version()))
+    }
+
+    pub fn supported_extensions(&self) -> Result<&HashSet<CString>> {
+        let extensions = self.extensions.get_or_init(|| {
+            let extensions = self.instance.backend().extensions(self.physical_device)?;
+            Ok(extensions.iter()
+                .map(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }.to_owned())
+                .collect())
+        });
+        extensions.as_ref().map_err(|result| Error::vulkan("vkEnumerateDeviceExtensionProperties")(*result))
     }
 
     pub fn name(&self) -> String {
-        unsafe {
-            CStr::from_ptr(self.instance.get_physical_device_properties(self.physical_device).device_name.as_ptr()).to_str().unwrap_or("").to_string()
-        }
+        c_string(&self.properties().device_name)
+    }
+
+    pub fn instance(&self) -> &Arc<Instance> {
+        &self.instance
     }
 
     pub fn handle(&self) -> vk::PhysicalDevice {
         self.physical_device
     }
+
+    /// Name the physical device for validation messages and debugging tools. Naming goes through a device created from the same instance, and does nothing without `VK_EXT_debug_utils`.
+    pub fn set_debug_name(&self, device: &Device, name: &str) -> Result<()> {
+        set_object_name(self.instance.debug_utils(), device.handle().handle(), self.physical_device, name)
+    }
 }
 
 impl Deref for PhysicalDevice {
@@ -134,4 +519,212 @@ impl Deref for PhysicalDevice {
     fn deref(&self) -> &Self::Target {
         &self.physical_device
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use crate::backend::fake::{instance, FakeDevice};
+    use crate::device_override::DeviceOverride;
+    use crate::error::Error;
+    use crate::features::DeviceFeatures;
+    use crate::limits::LimitRequirements;
+    use crate::report::RejectionReason;
+    use super::{GpuSelectionParameters, PhysicalDevice};
+
+    const DISCRETE: vk::PhysicalDeviceType = vk::PhysicalDeviceType::DISCRETE_GPU;
+    const INTEGRATED: vk::PhysicalDeviceType = vk::PhysicalDeviceType::INTEGRATED_GPU;
+    const CPU: vk::PhysicalDeviceType = vk::PhysicalDeviceType::CPU;
+
+    fn parameters<'a>() -> GpuSelectionParameters<'a> {
+        GpuSelectionParameters::new().env_override(false)
+    }
+
+    fn selected_name(devices: Vec<FakeDevice>, parameters: GpuSelectionParameters) -> String {
+        PhysicalDevice::select(instance(devices), parameters).unwrap().name()
+    }
+
+    #[test]
+    fn discrete_beats_integrated_beats_cpu() {
+        let devices = vec![
+            FakeDevice::new("cpu", CPU),
+            FakeDevice::new("integrated", INTEGRATED),
+            FakeDevice::new("discrete", DISCRETE),
+        ];
+        let ranked = PhysicalDevice::rank(instance(devices), &parameters()).unwrap();
+        let names: Vec<String> = ranked.iter().map(|ranked| ranked.device.name()).collect();
+        assert_eq!(names, ["discrete", "integrated", "cpu"]);
+    }
+
+    #[test]
+    fn device_type_outweighs_vram() {
+        let devices = vec![
+            FakeDevice::new("integrated", INTEGRATED).vram_mib(16 * 1024),
+            FakeDevice::new("discrete", DISCRETE).vram_mib(2 * 1024),
+        ];
+        assert_eq!(selected_name(devices, parameters()), "discrete");
+    }
+
+    #[test]
+    fn vram_breaks_type_ties() {
+        let devices = vec![
+            FakeDevice::new("small", DISCRETE).vram_mib(4 * 1024),
+            FakeDevice::new("large", DISCRETE).vram_mib(8 * 1024),
+        ];
+        assert_eq!(selected_name(devices, parameters()), "large");
+    }
+
+    #[test]
+    fn first_enumerated_device_wins_ties() {
+        let devices = vec![
+            FakeDevice::new("first", DISCRETE),
+            FakeDevice::new("second", DISCRETE),
+            FakeDevice::new("third", DISCRETE),
+        ];
+        assert_eq!(selected_name(devices.clone(), parameters()), "first");
+
+        let ranked = PhysicalDevice::rank(instance(devices), &parameters()).unwrap();
+        let names: Vec<String> = ranked.iter().map(|ranked| ranked.device.name()).collect();
+        assert_eq!(names, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn missing_extensions_are_reported() {
+        let devices = vec![
+            FakeDevice::new("no swapchain", DISCRETE).without_extension(ash::extensions::khr::Swapchain::name()),
+            FakeDevice::new("swapchain", INTEGRATED),
+        ];
+        let report = PhysicalDevice::evaluate(instance(devices), &parameters()).unwrap();
+        assert_eq!(report.devices[0].rejections, [RejectionReason::MissingExtensions(vec!["VK_KHR_swapchain".to_string()])]);
+        assert!(report.devices[1].is_compatible());
+        assert_eq!(report.devices[0].score, None);
+    }
+
+    #[test]
+    fn headless_does_not_require_swapchain() {
+        let devices = vec![FakeDevice::new("compute", DISCRETE).without_extension(ash::extensions::khr::Swapchain::name())];
+        assert_eq!(selected_name(devices, GpuSelectionParameters::headless().env_override(false)), "compute");
+    }
+
+    #[test]
+    fn extra_extensions_can_be_required() {
+        let devices = vec![
+            FakeDevice::new("plain", DISCRETE),
+            FakeDevice::new("ray tracing", INTEGRATED).extension(ash::extensions::khr::RayTracingPipeline::name()),
+        ];
+        let parameters = parameters().require_extension(ash::extensions::khr::RayTracingPipeline::name());
+        assert_eq!(selected_name(devices, parameters), "ray tracing");
+    }
+
+    #[test]
+    fn checkers_see_queue_families() {
+        let devices = vec![
+            FakeDevice::new("shared", DISCRETE),
+            FakeDevice::new("async compute", INTEGRATED)
+                .queue_families(&[(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE, 1), (vk::QueueFlags::COMPUTE, 2)]),
+        ];
+        let parameters = parameters().add_checker(|physical_device: &PhysicalDevice| {
+            physical_device.queue_families().iter().any(|family| family.is_dedicated_compute())
+        });
+        assert_eq!(selected_name(devices, parameters), "async compute");
+    }
+
+    #[test]
+    fn checker_veto_rejects_device() {
+        let devices = vec![
+            FakeDevice::new("vetoed", DISCRETE),
+            FakeDevice::new("accepted", INTEGRATED),
+        ];
+        let parameters = parameters().add_checker(|physical_device: &PhysicalDevice| physical_device.name() != "vetoed");
+
+        let report = PhysicalDevice::evaluate(instance(devices.clone()), &parameters).unwrap();
+        assert!(matches!(report.devices[0].rejections.as_slice(), [RejectionReason::CheckerVeto(_)]));
+        assert!(report.devices[1].is_compatible());
+
+        assert_eq!(selected_name(devices, parameters), "accepted");
+    }
+
+    #[test]
+    fn checkers_only_run_on_otherwise_compatible_devices() {
+        let devices = vec![FakeDevice::new("cpu", CPU)];
+        let parameters = parameters()
+            .deny_type(CPU)
+            .add_checker(|_: &PhysicalDevice| -> bool { panic!("checker called on a rejected device") });
+        let report = PhysicalDevice::evaluate(instance(devices), &parameters).unwrap();
+        assert_eq!(report.devices[0].rejections, [RejectionReason::DisallowedType(CPU)]);
+    }
+
+    #[test]
+    fn missing_features_are_reported() {
+        let devices = vec![
+            FakeDevice::new("without", DISCRETE),
+            FakeDevice::new("with", INTEGRATED).feature("sampler_anisotropy"),
+        ];
+        let mut features = DeviceFeatures::default();
+        features.set("sampler_anisotropy", true);
+        let parameters = parameters().require_features(features);
+
+        let report = PhysicalDevice::evaluate(instance(devices.clone()), &parameters).unwrap();
+        assert_eq!(report.devices[0].rejections, [RejectionReason::MissingFeatures(vec!["vulkan_1_0.sampler_anisotropy".to_string()])]);
+        assert_eq!(selected_name(devices, parameters), "with");
+    }
+
+    #[test]
+    fn unmet_limits_and_api_version_are_reported() {
+        let devices = vec![
+            FakeDevice::new("old", DISCRETE).api_version(vk::API_VERSION_1_0).limits(|limits| limits.max_image_dimension2_d = 4096),
+        ];
+        let parameters = parameters()
+            .min_api_version(vk::API_VERSION_1_2)
+            .require_limits(LimitRequirements::default().max_image_dimension_2d(8192));
+
+        let report = PhysicalDevice::evaluate(instance(devices), &parameters).unwrap();
+        let rejections = &report.devices[0].rejections;
+        assert!(rejections.contains(&RejectionReason::ApiVersionTooLow { required: vk::API_VERSION_1_2, actual: vk::API_VERSION_1_0 }));
+        assert!(rejections.iter().any(|rejection| matches!(rejection, RejectionReason::LimitNotMet(limit) if limit.required == 8192 && limit.actual == 4096)));
+    }
+
+    #[test]
+    fn no_compatible_device_returns_report() {
+        let devices = vec![FakeDevice::new("cpu", CPU)];
+        match PhysicalDevice::select(instance(devices), parameters().deny_type(CPU)) {
+            Err(Error::NoCompatibleDevice(report)) => {
+                assert_eq!(report.devices.len(), 1);
+                assert_eq!(report.devices[0].rejections, [RejectionReason::DisallowedType(CPU)]);
+            }
+            _ => panic!("expected NoCompatibleDevice"),
+        }
+    }
+
+    #[test]
+    fn device_override_pins_compatible_device() {
+        let devices = vec![
+            FakeDevice::new("discrete", DISCRETE),
+            FakeDevice::new("integrated", INTEGRATED).ids(0x8086, 0x46a6),
+        ];
+        assert_eq!(selected_name(devices.clone(), parameters().device_override(DeviceOverride::Index(1))), "integrated");
+        assert_eq!(selected_name(devices.clone(), parameters().device_override(DeviceOverride::Name("INTEG".to_string()))), "integrated");
+        assert_eq!(selected_name(devices.clone(), parameters().device_override(DeviceOverride::Id { vendor_id: 0x8086, device_id: 0x46a6 })), "integrated");
+        assert_eq!(selected_name(devices, parameters().device_override(DeviceOverride::Uuid([2; 16]))), "integrated");
+    }
+
+    #[test]
+    fn device_override_falls_back_when_incompatible_or_absent() {
+        let devices = vec![
+            FakeDevice::new("discrete", DISCRETE),
+            FakeDevice::new("cpu", CPU),
+        ];
+        assert_eq!(selected_name(devices.clone(), parameters().deny_type(CPU).device_override(DeviceOverride::Index(1))), "discrete");
+        assert_eq!(selected_name(devices, parameters().device_override(DeviceOverride::Name("missing".to_string()))), "discrete");
+    }
+
+    #[test]
+    fn device_override_skips_incompatible_matches() {
+        let devices = vec![
+            FakeDevice::new("discrete", DISCRETE),
+            FakeDevice::new("gpu cpu", CPU),
+            FakeDevice::new("gpu integrated", INTEGRATED),
+        ];
+        assert_eq!(selected_name(devices, parameters().deny_type(CPU).device_override(DeviceOverride::Name("gpu".to_string()))), "gpu integrated");
+    }
+}
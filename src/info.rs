@@ -0,0 +1,467 @@
+use std::collections::BTreeMap;
+use std::ffi::{c_char, CStr};
+use std::fmt::{Debug, Display, Formatter};
+use ash::vk;
+use serde::{Serialize, Serializer};
+use crate::gpu::PhysicalDevice;
+use crate::util::{format_api_version, format_uuid};
+
+/// GPU vendors decoded from the PCI vendor ID, or from the Khronos vendor ID for vendors without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Vendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Arm,
+    Qualcomm,
+    ImgTec,
+    Apple,
+    Microsoft,
+    Broadcom,
+    Samsung,
+    /// Mesa software drivers such as llvmpipe and lavapipe.
+    Mesa,
+    Unknown(u32),
+}
+
+impl Vendor {
+    pub fn from_id(vendor_id: u32) -> Self {
+        match vendor_id {
+            0x10DE => Vendor::Nvidia,
+            0x1002 | 0x1022 => Vendor::Amd,
+            0x8086 => Vendor::Intel,
+            0x13B5 => Vendor::Arm,
+            0x5143 => Vendor::Qualcomm,
+            0x1010 => Vendor::ImgTec,
+            0x106B => Vendor::Apple,
+            0x1414 => Vendor::Microsoft,
+            0x14E4 => Vendor::Broadcom,
+            0x144D => Vendor::Samsung,
+            0x10005 => Vendor::Mesa,
+            vendor_id => Vendor::Unknown(vendor_id),
+        }
+    }
+}
+
+impl Display for Vendor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Vendor::Nvidia => write!(f, "NVIDIA"),
+            Vendor::Amd => write!(f, "AMD"),
+            Vendor::Intel => write!(f, "Intel"),
+            Vendor::Arm => write!(f, "ARM"),
+            Vendor::Qualcomm => write!(f, "Qualcomm"),
+            Vendor::ImgTec => write!(f, "Imagination Technologies"),
+            Vendor::Apple => write!(f, "Apple"),
+            Vendor::Microsoft => write!(f, "Microsoft"),
+            Vendor::Broadcom => write!(f, "Broadcom"),
+            Vendor::Samsung => write!(f, "Samsung"),
+            Vendor::Mesa => write!(f, "Mesa"),
+            Vendor::Unknown(vendor_id) => write!(f, "unknown vendor 0x{:04x}", vendor_id),
+        }
+    }
+}
+
+/// Format a driver version with the encoding used by the vendor.
+///
+/// NVIDIA packs the version in 10/8/8/6 bits and the Intel Windows driver in 18/14 bits, other drivers follow the `vk::make_api_version` encoding.
+pub fn format_driver_version(vendor_id: u32, driver_version: u32) -> String {
+    match Vendor::from_id(vendor_id) {
+        Vendor::Nvidia => format!("{}.{}.{}.{}", driver_version >> 22, (driver_version >> 14) & 0xff, (driver_version >> 6) & 0xff, driver_version & 0x3f),
+        Vendor::Intel if cfg!(windows) => format!("{}.{}", driver_version >> 14, driver_version & 0x3fff),
+        _ => format_api_version(driver_version),
+    }
+}
+
+/// The formats of core Vulkan 1.0, `VK_FORMAT_R4G4_UNORM_PACK8` to `VK_FORMAT_ASTC_12x12_SRGB_BLOCK`.
+#[doc(hidden)]
+pub fn core_formats() -> impl Iterator<Item = vk::Format> {
+    (1..=184).map(vk::Format::from_raw)
+}
+
+fn serialize_debug<T: Debug, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:?}", value))
+}
+
+fn serialize_version<S: Serializer>(version: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_api_version(*version))
+}
+
+fn serialize_uuid<S: Serializer>(uuid: &Option<[u8; 16]>, serializer: S) -> Result<S::Ok, S::Error> {
+    match uuid {
+        Some(uuid) => serializer.collect_str(&format_uuid(uuid)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn c_string(chars: &[c_char]) -> String {
+    unsafe { CStr::from_ptr(chars.as_ptr()) }.to_string_lossy().into_owned()
+}
+
+/// The value of a device limit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum LimitValue {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f32),
+    Unsigneds(Vec<u64>),
+    Floats(Vec<f32>),
+    SampleCounts(#[serde(serialize_with = "serialize_debug")] vk::SampleCountFlags),
+}
+
+impl Display for LimitValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitValue::Unsigned(value) => write!(f, "{}", value),
+            LimitValue::Signed(value) => write!(f, "{}", value),
+            LimitValue::Float(value) => write!(f, "{}", value),
+            LimitValue::Unsigneds(values) => write!(f, "{:?}", values),
+            LimitValue::Floats(values) => write!(f, "{:?}", values),
+            LimitValue::SampleCounts(counts) => write!(f, "{:?}", counts),
+        }
+    }
+}
+
+trait IntoLimitValue {
+    fn into_limit_value(self) -> LimitValue;
+}
+
+impl IntoLimitValue for u32 {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Unsigned(self as u64)
+    }
+}
+
+impl IntoLimitValue for u64 {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Unsigned(self)
+    }
+}
+
+impl IntoLimitValue for usize {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Unsigned(self as u64)
+    }
+}
+
+impl IntoLimitValue for i32 {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Signed(self as i64)
+    }
+}
+
+impl IntoLimitValue for f32 {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Float(self)
+    }
+}
+
+impl<const N: usize> IntoLimitValue for [u32; N] {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Unsigneds(self.iter().map(|value| *value as u64).collect())
+    }
+}
+
+impl<const N: usize> IntoLimitValue for [f32; N] {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::Floats(self.to_vec())
+    }
+}
+
+impl IntoLimitValue for vk::SampleCountFlags {
+    fn into_limit_value(self) -> LimitValue {
+        LimitValue::SampleCounts(self)
+    }
+}
+
+macro_rules! limit_table {
+    ($limits:expr, [$($field:ident),* $(,)?]) => {
+        BTreeMap::from([$((stringify!($field).to_string(), $limits.$field.into_limit_value())),*])
+    };
+}
+
+pub(crate) fn limit_table(limits: &vk::PhysicalDeviceLimits) -> BTreeMap<String, LimitValue> {
+    limit_table!(limits, [
+        max_image_dimension1_d, max_image_dimension2_d, max_image_dimension3_d, max_image_dimension_cube,
+        max_image_array_layers, max_texel_buffer_elements, max_uniform_buffer_range, max_storage_buffer_range,
+        max_push_constants_size, max_memory_allocation_count, max_sampler_allocation_count,
+        buffer_image_granularity, sparse_address_space_size, max_bound_descriptor_sets,
+        max_per_stage_descriptor_samplers, max_per_stage_descriptor_uniform_buffers,
+        max_per_stage_descriptor_storage_buffers, max_per_stage_descriptor_sampled_images,
+        max_per_stage_descriptor_storage_images, max_per_stage_descriptor_input_attachments,
+        max_per_stage_resources, max_descriptor_set_samplers, max_descriptor_set_uniform_buffers,
+        max_descriptor_set_uniform_buffers_dynamic, max_descriptor_set_storage_buffers,
+        max_descriptor_set_storage_buffers_dynamic, max_descriptor_set_sampled_images,
+        max_descriptor_set_storage_images, max_descriptor_set_input_attachments, max_vertex_input_attributes,
+        max_vertex_input_bindings, max_vertex_input_attribute_offset, max_vertex_input_binding_stride,
+        max_vertex_output_components, max_tessellation_generation_level, max_tessellation_patch_size,
+        max_tessellation_control_per_vertex_input_components, max_tessellation_control_per_vertex_output_components,
+        max_tessellation_control_per_patch_output_components, max_tessellation_control_total_output_components,
+        max_tessellation_evaluation_input_components, max_tessellation_evaluation_output_components,
+        max_geometry_shader_invocations, max_geometry_input_components, max_geometry_output_components,
+        max_geometry_output_vertices, max_geometry_total_output_components, max_fragment_input_components,
+        max_fragment_output_attachments, max_fragment_dual_src_attachments, max_fragment_combined_output_resources,
+        max_compute_shared_memory_size, max_compute_work_group_count, max_compute_work_group_invocations,
+        max_compute_work_group_size, sub_pixel_precision_bits, sub_texel_precision_bits, mipmap_precision_bits,
+        max_draw_indexed_index_value, max_draw_indirect_count, max_sampler_lod_bias, max_sampler_anisotropy,
+        max_viewports, max_viewport_dimensions, viewport_bounds_range, viewport_sub_pixel_bits,
+        min_memory_map_alignment, min_texel_buffer_offset_alignment, min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment, min_texel_offset, max_texel_offset, min_texel_gather_offset,
+        max_texel_gather_offset, min_interpolation_offset, max_interpolation_offset,
+        sub_pixel_interpolation_offset_bits, max_framebuffer_width, max_framebuffer_height, max_framebuffer_layers,
+        framebuffer_color_sample_counts, framebuffer_depth_sample_counts, framebuffer_stencil_sample_counts,
+        framebuffer_no_attachments_sample_counts, max_color_attachments, sampled_image_color_sample_counts,
+        sampled_image_integer_sample_counts, sampled_image_depth_sample_counts, sampled_image_stencil_sample_counts,
+        storage_image_sample_counts, max_sample_mask_words, timestamp_compute_and_graphics, timestamp_period,
+        max_clip_distances, max_cull_distances, max_combined_clip_and_cull_distances, discrete_queue_priorities,
+        point_size_range, line_width_range, point_size_granularity, line_width_granularity, strict_lines,
+        standard_sample_locations, optimal_buffer_copy_offset_alignment, optimal_buffer_copy_row_pitch_alignment,
+        non_coherent_atom_size,
+    ])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryHeapInfo {
+    pub size: u64,
+    #[serde(serialize_with = "serialize_debug")]
+    pub flags: vk::MemoryHeapFlags,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryTypeInfo {
+    pub heap_index: u32,
+    #[serde(serialize_with = "serialize_debug")]
+    pub flags: vk::MemoryPropertyFlags,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueueFamilyInfo {
+    pub index: u32,
+    pub queue_count: u32,
+    #[serde(serialize_with = "serialize_debug")]
+    pub flags: vk::QueueFlags,
+    pub timestamp_valid_bits: u32,
+    pub min_image_transfer_granularity: [u32; 3],
+}
+
+/// Driver properties, only available from Vulkan 1.2.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DriverProperties {
+    #[serde(serialize_with = "serialize_debug")]
+    pub driver_id: vk::DriverId,
+    pub driver_name: String,
+    pub driver_info: String,
+    /// The version of the conformance test suite the driver passed, formatted as `major.minor.subminor.patch`.
+    pub conformance_version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PciBusInfo {
+    pub domain: u32,
+    pub bus: u32,
+    pub device: u32,
+    pub function: u32,
+}
+
+impl Display for PciBusInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}:{:02x}:{:02x}.{:x}", self.domain, self.bus, self.device, self.function)
+    }
+}
+
+/// A snapshot of everything a physical device reports, queried once and cached by `PhysicalDevice::info`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    #[serde(serialize_with = "serialize_debug")]
+    pub device_type: vk::PhysicalDeviceType,
+    pub vendor: Vendor,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// The version supported by the device driver, see `PhysicalDevice::supported_api_version`.
+    #[serde(serialize_with = "serialize_version")]
+    pub api_version: u32,
+    pub driver_version: u32,
+    /// `driver_version` formatted with the encoding of the vendor, see `format_driver_version`.
+    pub driver_version_name: String,
+    pub driver: Option<DriverProperties>,
+    #[serde(serialize_with = "serialize_uuid")]
+    pub device_uuid: Option<[u8; 16]>,
+    #[serde(serialize_with = "serialize_uuid")]
+    pub driver_uuid: Option<[u8; 16]>,
+    /// Only available with `VK_EXT_pci_bus_info`.
+    pub pci_bus: Option<PciBusInfo>,
+    pub limits: BTreeMap<String, LimitValue>,
+    pub memory_heaps: Vec<MemoryHeapInfo>,
+    pub memory_types: Vec<MemoryTypeInfo>,
+    pub queue_families: Vec<QueueFamilyInfo>,
+    /// Names of the supported extensions, sorted.
+    pub extensions: Vec<String>,
+    /// Names of the supported features, formatted as `group.name`.
+    pub features: Vec<String>,
+}
+
+impl DeviceInfo {
+    /// Collect every property of a physical device from its cached queries. Vulkan 1.1 properties are only queried from Vulkan 1.1 and driver properties from Vulkan 1.2.
+    pub fn query(physical_device: &PhysicalDevice) -> Self {
+        let mut extensions: Vec<String> = match physical_device.supported_extensions() {
+            Ok(extensions) => extensions.iter().map(|extension| extension.to_string_lossy().into_owned()).collect(),
+            Err(error) => {
+                log::warn!("could not list the extensions of {}: {}", c_string(&physical_device.properties().device_name), error);
+                vec![]
+            }
+        };
+        extensions.sort();
+
+        let extended = physical_device.extended_properties();
+        let properties = extended.properties;
+
+        let driver = extended.driver.map(|driver_properties| {
+            let conformance = driver_properties.conformance_version;
+            DriverProperties {
+                driver_id: driver_properties.driver_id,
+                driver_name: c_string(&driver_properties.driver_name),
+                driver_info: c_string(&driver_properties.driver_info),
+                conformance_version: format!("{}.{}.{}.{}", conformance.major, conformance.minor, conformance.subminor, conformance.patch),
+            }
+        });
+
+        let memory_properties = physical_device.memory_properties();
+        let memory_heaps = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize].iter()
+            .map(|heap| MemoryHeapInfo {
+                size: heap.size,
+                flags: heap.flags,
+            })
+            .collect();
+        let memory_types = memory_properties.memory_types[..memory_properties.memory_type_count as usize].iter()
+            .map(|memory_type| MemoryTypeInfo {
+                heap_index: memory_type.heap_index,
+                flags: memory_type.property_flags,
+            })
+            .collect();
+
+        let queue_families = physical_device.queue_families().into_iter()
+            .map(|family| QueueFamilyInfo {
+                index: family.index,
+                queue_count: family.properties.queue_count,
+                flags: family.properties.queue_flags,
+                timestamp_valid_bits: family.properties.timestamp_valid_bits,
+                min_image_transfer_granularity: [
+                    family.properties.min_image_transfer_granularity.width,
+                    family.properties.min_image_transfer_granularity.height,
+                    family.properties.min_image_transfer_granularity.depth,
+                ],
+            })
+            .collect();
+
+        let features = physical_device.features().enabled_names();
+
+        Self {
+            name: c_string(&properties.device_name),
+            device_type: properties.device_type,
+            vendor: Vendor::from_id(properties.vendor_id),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            api_version: properties.api_version,
+            driver_version: properties.driver_version,
+            driver_version_name: format_driver_version(properties.vendor_id, properties.driver_version),
+            driver,
+            device_uuid: extended.id.map(|id| id.device_uuid),
+            driver_uuid: extended.id.map(|id| id.driver_uuid),
+            pci_bus: extended.pci_bus.map(|pci_bus| PciBusInfo {
+                domain: pci_bus.pci_domain,
+                bus: pci_bus.pci_bus,
+                device: pci_bus.pci_device,
+                function: pci_bus.pci_function,
+            }),
+            limits: limit_table(&properties.limits),
+            memory_heaps,
+            memory_types,
+            queue_families,
+            extensions,
+            features,
+        }
+    }
+
+    /// Size of the largest device local heap in bytes.
+    pub fn vram(&self) -> u64 {
+        self.memory_heaps.iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A summary of the device: identity, driver and memory. Limits, extensions and features are left out.
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} ({:?})", self.name, self.device_type)?;
+        writeln!(f, "  vendor: {} (0x{:04x}), device 0x{:04x}", self.vendor, self.vendor_id, self.device_id)?;
+        writeln!(f, "  Vulkan {}", format_api_version(self.api_version))?;
+        match &self.driver {
+            Some(driver) => writeln!(f, "  driver: {} {} ({}, {:?}, conformance {})", driver.driver_name, self.driver_version_name, driver.driver_info, driver.driver_id, driver.conformance_version)?,
+            None => writeln!(f, "  driver: {}", self.driver_version_name)?,
+        }
+        if let Some(device_uuid) = &self.device_uuid {
+            writeln!(f, "  device UUID: {}", format_uuid(device_uuid))?;
+        }
+        if let Some(driver_uuid) = &self.driver_uuid {
+            writeln!(f, "  driver UUID: {}", format_uuid(driver_uuid))?;
+        }
+        if let Some(pci_bus) = &self.pci_bus {
+            writeln!(f, "  PCI bus: {}", pci_bus)?;
+        }
+        for (index, heap) in self.memory_heaps.iter().enumerate() {
+            writeln!(f, "  memory heap {}: {} MiB {:?}", index, heap.size / (1024 * 1024), heap.flags)?;
+        }
+        for family in &self.queue_families {
+            writeln!(f, "  queue family {}: {} x {:?}", family.index, family.queue_count, family.flags)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use super::{format_driver_version, Vendor};
+
+    #[test]
+    fn decodes_vendor_ids() {
+        assert_eq!(Vendor::from_id(0x10de), Vendor::Nvidia);
+        assert_eq!(Vendor::from_id(0x1002), Vendor::Amd);
+        assert_eq!(Vendor::from_id(0x1022), Vendor::Amd);
+        assert_eq!(Vendor::from_id(0x8086), Vendor::Intel);
+        assert_eq!(Vendor::from_id(0x10005), Vendor::Mesa);
+        assert_eq!(Vendor::from_id(0x1234), Vendor::Unknown(0x1234));
+        assert_eq!(Vendor::from_id(0x1234).to_string(), "unknown vendor 0x1234");
+    }
+
+    #[test]
+    fn formats_nvidia_driver_versions() {
+        let version = (535 << 22) | (104 << 14) | (5 << 6) | 3;
+        assert_eq!(format_driver_version(0x10de, version), "535.104.5.3");
+        assert_eq!(format_driver_version(0x10de, u32::MAX), "1023.255.255.63");
+    }
+
+    #[test]
+    fn formats_intel_driver_versions() {
+        let windows = (101 << 14) | 4502;
+        let mesa = vk::make_api_version(0, 23, 2, 1);
+        if cfg!(windows) {
+            assert_eq!(format_driver_version(0x8086, windows), "101.4502");
+        } else {
+            assert_eq!(format_driver_version(0x8086, mesa), "23.2.1");
+        }
+    }
+
+    #[test]
+    fn formats_other_driver_versions() {
+        let version = vk::make_api_version(0, 24, 0, 5);
+        assert_eq!(format_driver_version(0x10005, version), "24.0.5");
+        assert_eq!(format_driver_version(0x1002, version), "24.0.5");
+        assert_eq!(format_driver_version(0x1234, version), "24.0.5");
+    }
+}
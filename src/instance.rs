@@ -1,22 +1,42 @@
+use std::ffi::{CStr, CString};
 use std::ops::Deref;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ash::extensions::{ext, khr};
 use ash::vk;
-use raw_window_handle::{HasWindowHandle, RawWindowHandle, WindowHandle};
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use crate::backend::Backend;
+use crate::allocator::{AllocationCallbacks, HostAllocator};
+use crate::error::{Error, Result};
+use crate::debug::{set_object_name, DebugMessenger, DebugSettings, VALIDATION_LAYER_NAME};
+use crate::device::Device;
+use crate::diagnostics::Diagnostics;
 use crate::ffi_util::CStringArray;
+use crate::loader::{driver_loading_list, DirectDriver, DriverLoadingMode, LoaderInfo, LoaderSource};
+use crate::util::HandleOwnership;
 
 pub struct Instance {
     entry: ash::Entry,
     instance: ash::Instance,
+    backend: Box<dyn Backend>,
+    debug_messenger: Option<DebugMessenger>,
+    debug_utils: Option<ext::DebugUtils>,
+    api_version: u32,
+    loader_version: u32,
+    enabled_extensions: Vec<String>,
+    enabled_layers: Vec<String>,
+    ownership: HandleOwnership,
+    loader_info: LoaderInfo,
+    allocation_callbacks: Option<AllocationCallbacks>,
+    // Directly loaded drivers must stay loaded until the instance is destroyed.
+    _drivers: Vec<DirectDriver>,
 }
 
+/// Provides the instance extensions needed to create surfaces for a window system.
 pub trait SurfaceExtensionProvider {
-    fn get_surface_extension(&self) -> Option<Vec<String>>;
-}
-
-#[derive(Debug)]
-pub enum InstanceInitError {
-    VulkanLoadingError(ash::LoadingError),
-    InstanceCreateError(vk::Result),
+    /// Fails with `Error::UnsupportedPlatform` when there is no surface support for the window system in use.
+    fn get_surface_extension(&self) -> Result<Vec<String>>;
 }
 
 impl Instance {
@@ -24,39 +44,497 @@ impl Instance {
         &self.instance
     }
 
-    pub unsafe fn new(os_extension_provider: &dyn SurfaceExtensionProvider) -> Result<Rc<Instance>, InstanceInitError> {
-        match ash::Entry::load() {
-            Ok(entry) => {
-                let extensions = os_extension_provider.get_surface_extension().expect("Failed to get required instance extensions. Possibly unsupported system.");
-
-                let extensions_cstr_array = CStringArray::from_vec(&extensions);
-
-                let app_info = vk::ApplicationInfo::builder()
-                    .api_version(vk::API_VERSION_1_3)
-                    .build();
-
-                match entry.create_instance(
-                    &vk::InstanceCreateInfo::builder()
-                        .application_info(&app_info)
-                        .enabled_extension_names(extensions_cstr_array.as_ptr_slice()),
-                    None) {
-                    Ok(instance) => {
-                        Ok(Rc::new(Instance {
-                            entry,
-                            instance,
-                        }))
-                    }
-                    Err(e) => Err(InstanceInitError::InstanceCreateError(e)),
-                }
+    pub fn builder<'a>() -> InstanceBuilder<'a> {
+        InstanceBuilder::default()
+    }
+
+    /// Create an instance with the surface extensions given by `os_extension_provider`.
+    ///
+    /// # Safety
+    /// Loads the system Vulkan library, which runs arbitrary initialisation code from the loader.
+    pub unsafe fn new(os_extension_provider: &dyn SurfaceExtensionProvider) -> Result<Arc<Instance>> {
+        Self::builder().surface_extensions(os_extension_provider).build()
+    }
+
+    /// Create an instance without any window system integration, for compute only or offscreen rendering.
+    ///
+    /// # Safety
+    /// Loads the system Vulkan library, which runs arbitrary initialisation code from the loader.
+    pub unsafe fn new_headless() -> Result<Arc<Instance>> {
+        Self::builder().build()
+    }
+
+    /// Create an instance with validation and a debug messenger configured by `debug_settings`.
+    ///
+    /// # Safety
+    /// Loads the system Vulkan library, which runs arbitrary initialisation code from the loader.
+    pub unsafe fn new_with_debug(os_extension_provider: &dyn SurfaceExtensionProvider, debug_settings: DebugSettings) -> Result<Arc<Instance>> {
+        Self::builder().surface_extensions(os_extension_provider).debug(debug_settings).build()
+    }
+
+    /// Wrap an instance created elsewhere with the given API version.
+    ///
+    /// # Safety
+    /// `instance` must have been created from `entry` with `api_version`. The loader is reported as the system loader by `loader_info`. With `HandleOwnership::Owned` the instance is destroyed when the wrapper is dropped, so it must not be destroyed elsewhere.
+    pub unsafe fn from_raw(entry: ash::Entry, instance: ash::Instance, api_version: u32, ownership: HandleOwnership) -> Arc<Instance> {
+        Self::from_backend(entry, instance.clone(), Box::new(instance), api_version, ownership)
+    }
+
+    /// Like `from_raw`, with physical device queries going through `backend`.
+    pub(crate) unsafe fn from_backend(entry: ash::Entry, instance: ash::Instance, backend: Box<dyn Backend>, api_version: u32, ownership: HandleOwnership) -> Arc<Instance> {
+        let loader_version = loader_version(&entry);
+        Arc::new(Instance {
+            loader_version,
+            entry,
+            instance,
+            backend,
+            debug_messenger: None,
+            debug_utils: None,
+            api_version,
+            enabled_extensions: vec![],
+            enabled_layers: vec![],
+            ownership,
+            loader_info: LoaderInfo {
+                source: LoaderSource::System,
+                version: loader_version,
+                drivers: vec![],
+                driver_mode: None,
             },
-            Err(loading_error) => Err(InstanceInitError::VulkanLoadingError(loading_error)),
-        }
+            allocation_callbacks: None,
+            _drivers: vec![],
+        })
     }
 
+    /// The physical device queries, made through `ash::Instance` outside of tests.
+    pub(crate) fn backend(&self) -> &dyn Backend {
+        self.backend.as_ref()
+    }
 
     pub fn entry(&self) -> &ash::Entry {
         &self.entry
     }
+
+    /// The API version the instance was created with, negotiated with the loader by `InstanceBuilder::build`.
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// The highest instance version supported by the loader, as reported by `vkEnumerateInstanceVersion`.
+    pub fn loader_version(&self) -> u32 {
+        self.loader_version
+    }
+
+    pub fn enabled_extensions(&self) -> &[String] {
+        &self.enabled_extensions
+    }
+
+    /// Whether the instance was created with `extension`, either requested or optional.
+    pub fn is_extension_enabled(&self, extension: &str) -> bool {
+        self.enabled_extensions.iter().any(|enabled| enabled == extension)
+    }
+
+    pub fn enabled_layers(&self) -> &[String] {
+        &self.enabled_layers
+    }
+
+    /// The loader and drivers the instance was created with.
+    pub fn loader_info(&self) -> &LoaderInfo {
+        &self.loader_info
+    }
+
+    /// The host allocator given to `InstanceBuilder::allocator`.
+    pub fn allocator(&self) -> Option<&Arc<dyn HostAllocator>> {
+        self.allocation_callbacks.as_ref().map(|callbacks| callbacks.allocator())
+    }
+
+    /// The allocation callbacks to create and destroy objects of this instance with, `None` when no host allocator is used.
+    pub fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks> {
+        self.allocation_callbacks.as_ref().map(|callbacks| callbacks.callbacks())
+    }
+
+    /// Whether debug messages are received through `VK_EXT_debug_utils`.
+    pub fn debug_messenger_enabled(&self) -> bool {
+        self.debug_messenger.is_some()
+    }
+
+    /// The `VK_EXT_debug_utils` functions, `None` when the extension is not enabled.
+    pub(crate) fn debug_utils(&self) -> Option<&ext::DebugUtils> {
+        self.debug_utils.as_ref()
+    }
+
+    /// Name the instance for validation messages and debugging tools. Naming goes through a device created from this instance, and does nothing without `VK_EXT_debug_utils`.
+    pub fn set_debug_name(&self, device: &Device, name: &str) -> Result<()> {
+        set_object_name(self.debug_utils(), device.handle().handle(), self.instance.handle(), name)
+    }
+}
+
+/// Configures and creates an `Instance`.
+///
+/// Requested extensions and layers are checked against the ones available before creating the instance, unavailable ones are reported by `Error::MissingInstanceExtensions` and `Error::MissingLayers`.
+pub struct InstanceBuilder<'a> {
+    application_name: String,
+    application_version: u32,
+    engine_name: String,
+    engine_version: u32,
+    api_version: u32,
+    surface_extension_provider: Option<&'a dyn SurfaceExtensionProvider>,
+    headless_surface: bool,
+    extensions: Vec<String>,
+    optional_extensions: Vec<String>,
+    layers: Vec<String>,
+    debug_settings: Option<DebugSettings>,
+    loader: LoaderSource,
+    drivers: Vec<PathBuf>,
+    driver_mode: DriverLoadingMode,
+    allocator: Option<Arc<dyn HostAllocator>>,
+}
+
+impl Default for InstanceBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            application_name: String::new(),
+            application_version: 0,
+            engine_name: "graphicat".to_string(),
+            engine_version: graphicat_version(),
+            api_version: vk::API_VERSION_1_3,
+            surface_extension_provider: None,
+            headless_surface: false,
+            extensions: vec![],
+            optional_extensions: vec![],
+            layers: vec![],
+            debug_settings: None,
+            loader: LoaderSource::System,
+            drivers: vec![],
+            driver_mode: DriverLoadingMode::Exclusive,
+            allocator: None,
+        }
+    }
+}
+
+impl<'a> InstanceBuilder<'a> {
+    pub fn application_name(mut self, name: &str) -> Self {
+        self.application_name = name.to_string();
+        self
+    }
+
+    /// Version of the application, created with `vk::make_api_version`.
+    pub fn application_version(mut self, version: u32) -> Self {
+        self.application_version = version;
+        self
+    }
+
+    /// Defaults to `graphicat`.
+    pub fn engine_name(mut self, name: &str) -> Self {
+        self.engine_name = name.to_string();
+        self
+    }
+
+    /// Defaults to the version of graphicat.
+    pub fn engine_version(mut self, version: u32) -> Self {
+        self.engine_version = version;
+        self
+    }
+
+    /// The highest Vulkan version the application can use. Defaults to Vulkan 1.3.
+    ///
+    /// The instance is created with the lower of this version and the one supported by the loader, so that it can still be created on loaders only supporting an older version.
+    pub fn api_version(mut self, version: u32) -> Self {
+        self.api_version = version;
+        self
+    }
+
+    /// Enable the instance extensions needed to create surfaces for a window system. Without it the instance is headless and no surface extension is enabled.
+    pub fn surface_extensions(mut self, provider: &'a dyn SurfaceExtensionProvider) -> Self {
+        self.surface_extension_provider = Some(provider);
+        self
+    }
+
+    /// Enable `VK_EXT_headless_surface`, to create surfaces not tied to any window with `Surface::new_headless`.
+    pub fn headless_surface(mut self, enabled: bool) -> Self {
+        self.headless_surface = enabled;
+        self
+    }
+
+    pub fn extension(mut self, extension: &str) -> Self {
+        if !self.extensions.iter().any(|e| e == extension) {
+            self.extensions.push(extension.to_string());
+        }
+        self
+    }
+
+    pub fn extensions<I: IntoIterator<Item = S>, S: AsRef<str>>(self, extensions: I) -> Self {
+        extensions.into_iter().fold(self, |builder, extension| builder.extension(extension.as_ref()))
+    }
+
+    /// Enable an extension if it is available, without failing instance creation otherwise. Check `Instance::is_extension_enabled` to know whether it was enabled.
+    pub fn optional_extension(mut self, extension: &str) -> Self {
+        if !self.optional_extensions.iter().any(|e| e == extension) {
+            self.optional_extensions.push(extension.to_string());
+        }
+        self
+    }
+
+    pub fn optional_extensions<I: IntoIterator<Item = S>, S: AsRef<str>>(self, extensions: I) -> Self {
+        extensions.into_iter().fold(self, |builder, extension| builder.optional_extension(extension.as_ref()))
+    }
+
+    pub fn layer(mut self, layer: &str) -> Self {
+        if !self.layers.iter().any(|l| l == layer) {
+            self.layers.push(layer.to_string());
+        }
+        self
+    }
+
+    pub fn layers<I: IntoIterator<Item = S>, S: AsRef<str>>(self, layers: I) -> Self {
+        layers.into_iter().fold(self, |builder, layer| builder.layer(layer.as_ref()))
+    }
+
+    /// Enable validation and a debug messenger.
+    pub fn debug(mut self, debug_settings: DebugSettings) -> Self {
+        self.debug_settings = Some(debug_settings);
+        self
+    }
+
+    /// Load the Vulkan loader from `path` instead of the system one, e.g. a specific `libvulkan.so.1`.
+    pub fn loader_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.loader = LoaderSource::Path(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Load a driver (ICD) library directly, e.g. `libvulkan_lvp.so` for lavapipe, without going through the ICD manifests.
+    ///
+    /// Requires a loader supporting `VK_LUNARG_direct_driver_loading`. How the driver is combined with the drivers found by the loader is set by `driver_mode`.
+    pub fn driver<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        if !self.drivers.contains(&path) {
+            self.drivers.push(path);
+        }
+        self
+    }
+
+    pub fn drivers<I: IntoIterator<Item = P>, P: AsRef<Path>>(self, paths: I) -> Self {
+        paths.into_iter().fold(self, |builder, path| builder.driver(path))
+    }
+
+    /// Defaults to `DriverLoadingMode::Exclusive`, only the drivers given to `driver` are used.
+    pub fn driver_mode(mut self, mode: DriverLoadingMode) -> Self {
+        self.driver_mode = mode;
+        self
+    }
+
+    /// Use `allocator` for the host memory allocations of the instance and of every object created from it, see `TrackingAllocator` for memory accounting.
+    pub fn allocator(mut self, allocator: Arc<dyn HostAllocator>) -> Self {
+        self.allocator = Some(allocator);
+        self
+    }
+
+    /// # Safety
+    /// Loads the Vulkan loader and the drivers given to `driver`, which runs arbitrary initialisation code from these libraries.
+    pub unsafe fn build(self) -> Result<Arc<Instance>> {
+        let entry = self.loader.load()?;
+        let drivers = self.drivers.iter().map(|path| DirectDriver::load(path)).collect::<Result<Vec<_>>>()?;
+
+        let mut extensions = self.extensions;
+        let mut layers = self.layers;
+        let mut debug_settings = self.debug_settings;
+
+        let mut surface_extensions = vec![];
+        if let Some(provider) = self.surface_extension_provider {
+            surface_extensions = provider.get_surface_extension()?;
+        }
+        if self.headless_surface {
+            surface_extensions.push(khr::Surface::name().to_string_lossy().into_owned());
+            surface_extensions.push(ext::HeadlessSurface::name().to_string_lossy().into_owned());
+        }
+        for extension in surface_extensions {
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+
+        let available_layers = available_layers(&entry);
+        let missing_layers: Vec<String> = layers.iter().filter(|layer| !available_layers.contains(layer)).cloned().collect();
+        if !missing_layers.is_empty() {
+            return Err(Error::MissingLayers(missing_layers));
+        }
+
+        let mut debug_utils_enabled = false;
+        if let Some(debug_settings) = &debug_settings {
+            if debug_settings.validation_enabled() && !layers.iter().any(|layer| layer == VALIDATION_LAYER_NAME) {
+                if available_layers.iter().any(|layer| layer == VALIDATION_LAYER_NAME) {
+                    layers.push(VALIDATION_LAYER_NAME.to_string());
+                } else {
+                    log::warn!("{} was requested but is not installed, continuing without validation", VALIDATION_LAYER_NAME);
+                }
+            }
+
+            let debug_utils_name = ext::DebugUtils::name().to_string_lossy().into_owned();
+            debug_utils_enabled = available_extensions(&entry, &layers).contains(&debug_utils_name);
+            if !debug_utils_enabled {
+                log::warn!("{} is not available, no debug messages will be received", debug_utils_name);
+            } else if !extensions.contains(&debug_utils_name) {
+                extensions.push(debug_utils_name);
+            }
+        }
+
+        let direct_driver_loading_name = vk::LunargDirectDriverLoadingFn::name().to_string_lossy().into_owned();
+        if !drivers.is_empty() && !extensions.contains(&direct_driver_loading_name) {
+            extensions.push(direct_driver_loading_name);
+        }
+
+        let available_extensions = available_extensions(&entry, &layers);
+        for extension in self.optional_extensions {
+            if extensions.contains(&extension) {
+                continue;
+            }
+            if available_extensions.contains(&extension) {
+                extensions.push(extension);
+            } else {
+                log::info!("optional instance extension {} is not available", extension);
+            }
+        }
+
+        let missing_extensions: Vec<String> = extensions.iter().filter(|extension| !available_extensions.contains(extension)).cloned().collect();
+        if !missing_extensions.is_empty() {
+            return Err(Error::MissingInstanceExtensions(missing_extensions));
+        }
+
+        // Vulkan 1.0 loaders fail instance creation for any version above 1.0, newer loaders accept any version.
+        let loader_version = loader_version(&entry);
+        let api_version = negotiate_api_version(self.api_version, loader_version);
+
+        let extensions_cstr_array = CStringArray::from_vec(&extensions);
+        let layers_cstr_array = CStringArray::from_vec(&layers);
+        let application_name = CString::new(self.application_name).unwrap_or_default();
+        let engine_name = CString::new(self.engine_name).unwrap_or_default();
+
+        let app_info = vk::ApplicationInfo::builder()
+            .application_name(&application_name)
+            .application_version(self.application_version)
+            .engine_name(&engine_name)
+            .engine_version(self.engine_version)
+            .api_version(api_version)
+            .build();
+
+        let mut create_info = vk::InstanceCreateInfo::builder()
+            .application_info(&app_info)
+            .enabled_extension_names(extensions_cstr_array.as_ptr_slice())
+            .enabled_layer_names(layers_cstr_array.as_ptr_slice());
+
+        // Chaining the messenger create info also reports messages emitted while creating and destroying the instance.
+        let debug_sink = debug_settings.as_mut().filter(|_| debug_utils_enabled).map(|debug_settings| debug_settings.take_sink());
+        let mut messenger_create_info = match (&debug_settings, &debug_sink) {
+            (Some(debug_settings), Some(debug_sink)) => Some(debug_settings.messenger_create_info(debug_sink)),
+            _ => None,
+        };
+        if let Some(messenger_create_info) = &mut messenger_create_info {
+            create_info = create_info.push_next(messenger_create_info);
+        }
+
+        let driver_infos: Vec<vk::DirectDriverLoadingInfoLUNARG> = drivers.iter().map(|driver| driver.loading_info()).collect();
+        let mut driver_loading_list = driver_loading_list(self.driver_mode, &driver_infos);
+        if !drivers.is_empty() {
+            create_info = create_info.push_next(&mut driver_loading_list);
+        }
+
+        let loader_info = LoaderInfo {
+            source: self.loader,
+            version: loader_version,
+            drivers: drivers.iter().map(|driver| driver.path.clone()).collect(),
+            driver_mode: if drivers.is_empty() { None } else { Some(self.driver_mode) },
+        };
+        log::info!("Creating Vulkan instance with the {}", loader_info);
+
+        let allocation_callbacks = self.allocator.map(AllocationCallbacks::new);
+        let callbacks = allocation_callbacks.as_ref().map(|callbacks| callbacks.callbacks());
+        let instance = entry.create_instance(&create_info, callbacks).map_err(|result| match result {
+            vk::Result::ERROR_INCOMPATIBLE_DRIVER => Error::NoDriver {
+                diagnostics: Box::new(Diagnostics::collect()),
+            },
+            result => Error::vulkan("vkCreateInstance")(result),
+        })?;
+
+        let debug_utils_name = ext::DebugUtils::name().to_string_lossy().into_owned();
+        let debug_utils = extensions.contains(&debug_utils_name).then(|| ext::DebugUtils::new(&entry, &instance));
+
+        let debug_messenger = match (&debug_settings, debug_sink) {
+            (Some(debug_settings), Some(debug_sink)) => match DebugMessenger::new(&entry, &instance, debug_settings, debug_sink, callbacks) {
+                Ok(debug_messenger) => Some(debug_messenger),
+                Err(result) => {
+                    instance.destroy_instance(callbacks);
+                    return Err(Error::vulkan("vkCreateDebugUtilsMessengerEXT")(result));
+                }
+            },
+            _ => None,
+        };
+
+        Ok(Arc::new(Instance {
+            entry,
+            backend: Box::new(instance.clone()),
+            instance,
+            debug_messenger,
+            debug_utils,
+            api_version,
+            loader_version,
+            enabled_extensions: extensions,
+            enabled_layers: layers,
+            ownership: HandleOwnership::Owned,
+            loader_info,
+            allocation_callbacks,
+            _drivers: drivers,
+        }))
+    }
+}
+
+unsafe fn loader_version(entry: &ash::Entry) -> u32 {
+    match entry.try_enumerate_instance_version() {
+        Ok(Some(version)) => version,
+        _ => vk::API_VERSION_1_0,
+    }
+}
+
+/// The lower of the two versions without patch version, which does not change the API.
+fn negotiate_api_version(requested: u32, loader_version: u32) -> u32 {
+    let without_patch = |version: u32| vk::make_api_version(0, vk::api_version_major(version), vk::api_version_minor(version), 0);
+    without_patch(requested).min(without_patch(loader_version))
+}
+
+fn graphicat_version() -> u32 {
+    let mut version = env!("CARGO_PKG_VERSION").split('.').map(|part| part.parse().unwrap_or(0));
+    vk::make_api_version(0, version.next().unwrap_or(0), version.next().unwrap_or(0), version.next().unwrap_or(0))
+}
+
+unsafe fn available_layers(entry: &ash::Entry) -> Vec<String> {
+    entry.enumerate_instance_layer_properties().unwrap_or_default().iter()
+        .map(|layer| CStr::from_ptr(layer.layer_name.as_ptr()).to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Extensions provided by the implementation and by the given layers.
+unsafe fn available_extensions(entry: &ash::Entry, layers: &[String]) -> Vec<String> {
+    let mut extensions = entry.enumerate_instance_extension_properties(None).unwrap_or_default();
+    for layer in layers {
+        if let Ok(layer) = CString::new(layer.as_str()) {
+            extensions.extend(entry.enumerate_instance_extension_properties(Some(&layer)).unwrap_or_default());
+        }
+    }
+
+    extensions.iter()
+        .map(|extension| CStr::from_ptr(extension.extension_name.as_ptr()).to_string_lossy().into_owned())
+        .collect()
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        if self.ownership == HandleOwnership::Owned {
+            unsafe {
+                if let Some(debug_messenger) = &self.debug_messenger {
+                    debug_messenger.destroy(self.allocation_callbacks());
+                }
+                self.instance.destroy_instance(self.allocation_callbacks());
+            }
+        }
+    }
 }
 
 impl Deref for Instance {
@@ -70,8 +548,10 @@ impl Deref for Instance {
 
 #[cfg(feature = "glfw")]
 impl SurfaceExtensionProvider for glfw::Glfw {
-    fn get_surface_extension(&self) -> Option<Vec<String>> {
-        self.get_required_instance_extensions()
+    fn get_surface_extension(&self) -> Result<Vec<String>> {
+        self.get_required_instance_extensions().ok_or_else(|| Error::UnsupportedPlatform {
+            platform: "glfw without Vulkan support".to_string(),
+        })
     }
 }
 
@@ -83,33 +563,89 @@ pub struct RwhExtensionProvider {
 
 #[cfg(feature = "raw-window-handle")]
 impl RwhExtensionProvider {
-    pub fn new<T: HasWindowHandle>(o: &T) -> Self {
-        Self {
-            window_handle: o.window_handle().unwrap().as_raw(),
-        }
+    pub fn new<T: HasWindowHandle>(o: &T) -> Result<Self> {
+        let window_handle = o.window_handle().map_err(|error| Error::InvalidWindowHandle {
+            platform: "raw-window-handle".to_string(),
+            reason: error.to_string(),
+        })?;
+
+        Ok(Self {
+            window_handle: window_handle.as_raw(),
+        })
     }
 }
 
 #[cfg(feature = "raw-window-handle")]
 impl SurfaceExtensionProvider for RwhExtensionProvider {
-    fn get_surface_extension(&self) -> Option<Vec<String>> {
+    fn get_surface_extension(&self) -> Result<Vec<String>> {
+        let unsupported = || Err(Error::UnsupportedPlatform {
+            platform: window_system_name(&self.window_handle).to_string(),
+        });
+
         match self.window_handle {
-            RawWindowHandle::UiKit(_) => None,
-            RawWindowHandle::AppKit(_) => None,
-            RawWindowHandle::Orbital(_) => None,
-            RawWindowHandle::Xlib(_) => Some(vec!["VK_KHR_xlib_surface".to_string(), "VK_KHR_surface".to_string()]),
-            RawWindowHandle::Xcb(_) => Some(vec!["VK_KHR_xcb_surface".to_string(), "VK_KHR_surface".to_string()]),
-            RawWindowHandle::Wayland(_) => Some(vec!["VK_KHR_wayland_surface".to_string(), "VK_KHR_surface".to_string()]),
-            RawWindowHandle::Drm(_) => None,
-            RawWindowHandle::Gbm(_) => None,
-            RawWindowHandle::Win32(_) => Some(vec!["VK_KHR_win32_surface".to_string(), "VK_KHR_surface".to_string()]),
-            RawWindowHandle::WinRt(_) => None,
-            RawWindowHandle::Web(_) => None,
-            RawWindowHandle::WebCanvas(_) => None,
-            RawWindowHandle::WebOffscreenCanvas(_) => None,
-            RawWindowHandle::AndroidNdk(_) => None,
-            RawWindowHandle::Haiku(_) => None,
-            _ => None
-        }
-    }
-}
\ No newline at end of file
+            RawWindowHandle::UiKit(_) => unsupported(),
+            RawWindowHandle::AppKit(_) => unsupported(),
+            RawWindowHandle::Orbital(_) => unsupported(),
+            RawWindowHandle::Xlib(_) => Ok(vec!["VK_KHR_xlib_surface".to_string(), "VK_KHR_surface".to_string()]),
+            RawWindowHandle::Xcb(_) => Ok(vec!["VK_KHR_xcb_surface".to_string(), "VK_KHR_surface".to_string()]),
+            RawWindowHandle::Wayland(_) => Ok(vec!["VK_KHR_wayland_surface".to_string(), "VK_KHR_surface".to_string()]),
+            RawWindowHandle::Drm(_) => unsupported(),
+            RawWindowHandle::Gbm(_) => unsupported(),
+            RawWindowHandle::Win32(_) => Ok(vec!["VK_KHR_win32_surface".to_string(), "VK_KHR_surface".to_string()]),
+            RawWindowHandle::WinRt(_) => unsupported(),
+            RawWindowHandle::Web(_) => unsupported(),
+            RawWindowHandle::WebCanvas(_) => unsupported(),
+            RawWindowHandle::WebOffscreenCanvas(_) => unsupported(),
+            RawWindowHandle::AndroidNdk(_) => unsupported(),
+            RawWindowHandle::Haiku(_) => unsupported(),
+            _ => unsupported(),
+        }
+    }
+}
+
+/// Name of the window system of a window handle, for error messages.
+#[cfg(feature = "raw-window-handle")]
+pub(crate) fn window_system_name(window_handle: &RawWindowHandle) -> &'static str {
+    match window_handle {
+        RawWindowHandle::UiKit(_) => "UIKit",
+        RawWindowHandle::AppKit(_) => "AppKit",
+        RawWindowHandle::Orbital(_) => "Orbital",
+        RawWindowHandle::Xlib(_) => "Xlib",
+        RawWindowHandle::Xcb(_) => "XCB",
+        RawWindowHandle::Wayland(_) => "Wayland",
+        RawWindowHandle::Drm(_) => "DRM",
+        RawWindowHandle::Gbm(_) => "GBM",
+        RawWindowHandle::Win32(_) => "Win32",
+        RawWindowHandle::WinRt(_) => "WinRT",
+        RawWindowHandle::Web(_) => "Web",
+        RawWindowHandle::WebCanvas(_) => "Web canvas",
+        RawWindowHandle::WebOffscreenCanvas(_) => "Web offscreen canvas",
+        RawWindowHandle::AndroidNdk(_) => "Android NDK",
+        RawWindowHandle::Haiku(_) => "Haiku",
+        _ => "an unknown window system",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use crate::util::format_api_version;
+    use super::negotiate_api_version;
+
+    #[test]
+    fn negotiates_the_lower_version() {
+        let version = |minor, patch| vk::make_api_version(0, 1, minor, patch);
+        for (requested, loader, expected) in [
+            (version(3, 0), version(3, 250), version(3, 0)),
+            (version(3, 0), version(2, 198), version(2, 0)),
+            (version(2, 0), version(3, 250), version(2, 0)),
+            (version(2, 5), version(3, 250), version(2, 0)),
+            (version(3, 5), version(3, 250), version(3, 0)),
+            (version(3, 0), version(0, 0), version(0, 0)),
+            (version(1, 0), version(0, 65), version(0, 0)),
+            (version(0, 0), version(3, 250), version(0, 0)),
+        ] {
+            assert_eq!(negotiate_api_version(requested, loader), expected, "requested {}, loader {}", format_api_version(requested), format_api_version(loader));
+        }
+    }
+}
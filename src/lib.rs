@@ -1,5 +1,22 @@
+pub mod error;
 pub mod instance;
+pub mod loader;
+pub mod allocator;
 pub mod surface;
+pub mod debug;
 pub mod gpu;
+pub mod device;
+pub mod device_override;
+pub mod queue;
+pub mod features;
+pub mod limits;
+pub mod scoring;
+pub mod report;
+pub mod info;
+pub mod diagnostics;
+pub mod profiles;
 pub mod ffi_util;
+mod backend;
 pub mod util;
+
+pub use error::{Error, Result};
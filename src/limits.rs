@@ -0,0 +1,163 @@
+use ash::vk;
+
+/// Minimum values a device has to reach for some of its `vk::PhysicalDeviceLimits`. Limits left to `None` are not checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitRequirements {
+    pub max_image_dimension_2d: Option<u32>,
+    pub max_push_constants_size: Option<u32>,
+    pub max_bound_descriptor_sets: Option<u32>,
+    pub max_compute_work_group_size: Option<[u32; 3]>,
+    pub max_compute_work_group_invocations: Option<u32>,
+}
+
+/// A limit a device does not reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnmetLimit {
+    /// Name of the field of `vk::PhysicalDeviceLimits`, with the component for array limits.
+    pub limit: &'static str,
+    pub required: u64,
+    pub actual: u64,
+}
+
+impl LimitRequirements {
+    pub fn max_image_dimension_2d(mut self, value: u32) -> Self {
+        self.max_image_dimension_2d = Some(value);
+        self
+    }
+
+    pub fn max_push_constants_size(mut self, value: u32) -> Self {
+        self.max_push_constants_size = Some(value);
+        self
+    }
+
+    pub fn max_bound_descriptor_sets(mut self, value: u32) -> Self {
+        self.max_bound_descriptor_sets = Some(value);
+        self
+    }
+
+    pub fn max_compute_work_group_size(mut self, value: [u32; 3]) -> Self {
+        self.max_compute_work_group_size = Some(value);
+        self
+    }
+
+    pub fn max_compute_work_group_invocations(mut self, value: u32) -> Self {
+        self.max_compute_work_group_invocations = Some(value);
+        self
+    }
+
+    /// Keep the highest requirement of `self` and `other` for every limit.
+    pub fn merge(self, other: &LimitRequirements) -> Self {
+        fn max<T: Ord + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+            a.max(b)
+        }
+
+        Self {
+            max_image_dimension_2d: max(self.max_image_dimension_2d, other.max_image_dimension_2d),
+            max_push_constants_size: max(self.max_push_constants_size, other.max_push_constants_size),
+            max_bound_descriptor_sets: max(self.max_bound_descriptor_sets, other.max_bound_descriptor_sets),
+            max_compute_work_group_size: match (self.max_compute_work_group_size, other.max_compute_work_group_size) {
+                (Some(a), Some(b)) => Some([a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])]),
+                (a, b) => a.or(b),
+            },
+            max_compute_work_group_invocations: max(self.max_compute_work_group_invocations, other.max_compute_work_group_invocations),
+        }
+    }
+
+    /// Every required limit the device does not reach.
+    pub fn unmet(&self, limits: &vk::PhysicalDeviceLimits) -> Vec<UnmetLimit> {
+        let mut checks = vec![
+            ("max_image_dimension2_d", self.max_image_dimension_2d, limits.max_image_dimension2_d),
+            ("max_push_constants_size", self.max_push_constants_size, limits.max_push_constants_size),
+            ("max_bound_descriptor_sets", self.max_bound_descriptor_sets, limits.max_bound_descriptor_sets),
+            ("max_compute_work_group_invocations", self.max_compute_work_group_invocations, limits.max_compute_work_group_invocations),
+        ];
+
+        if let Some(size) = self.max_compute_work_group_size {
+            checks.push(("max_compute_work_group_size[0]", Some(size[0]), limits.max_compute_work_group_size[0]));
+            checks.push(("max_compute_work_group_size[1]", Some(size[1]), limits.max_compute_work_group_size[1]));
+            checks.push(("max_compute_work_group_size[2]", Some(size[2]), limits.max_compute_work_group_size[2]));
+        }
+
+        checks.into_iter()
+            .filter_map(|(limit, required, actual)| match required {
+                Some(required) if actual < required => Some(UnmetLimit {
+                    limit,
+                    required: required as u64,
+                    actual: actual as u64,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use super::{LimitRequirements, UnmetLimit};
+
+    fn limits() -> vk::PhysicalDeviceLimits {
+        vk::PhysicalDeviceLimits {
+            max_image_dimension2_d: 8192,
+            max_push_constants_size: 128,
+            max_bound_descriptor_sets: 8,
+            max_compute_work_group_size: [1024, 1024, 64],
+            max_compute_work_group_invocations: 1024,
+            ..Default::default()
+        }
+    }
+
+    fn unmet(requirements: LimitRequirements) -> Vec<(&'static str, u64, u64)> {
+        requirements.unmet(&limits()).into_iter()
+            .map(|UnmetLimit { limit, required, actual }| (limit, required, actual))
+            .collect()
+    }
+
+    #[test]
+    fn merge_keeps_the_highest_requirements() {
+        let a = LimitRequirements::default()
+            .max_image_dimension_2d(4096)
+            .max_push_constants_size(256)
+            .max_compute_work_group_size([512, 1024, 16]);
+        let b = LimitRequirements::default()
+            .max_image_dimension_2d(16384)
+            .max_bound_descriptor_sets(4)
+            .max_compute_work_group_size([1024, 256, 64]);
+
+        let expected = LimitRequirements {
+            max_image_dimension_2d: Some(16384),
+            max_push_constants_size: Some(256),
+            max_bound_descriptor_sets: Some(4),
+            max_compute_work_group_size: Some([1024, 1024, 64]),
+            max_compute_work_group_invocations: None,
+        };
+        assert_eq!(a.merge(&b), expected);
+        assert_eq!(b.merge(&a), expected);
+        assert_eq!(a.merge(&LimitRequirements::default()), a);
+        assert_eq!(LimitRequirements::default().merge(&b), b);
+    }
+
+    #[test]
+    fn reached_limits_are_met() {
+        let requirements = LimitRequirements::default()
+            .max_image_dimension_2d(8192)
+            .max_push_constants_size(128)
+            .max_bound_descriptor_sets(8)
+            .max_compute_work_group_size([1024, 1024, 64])
+            .max_compute_work_group_invocations(1024);
+        assert_eq!(unmet(requirements), []);
+        assert_eq!(unmet(LimitRequirements::default()), []);
+    }
+
+    #[test]
+    fn reports_each_unmet_limit() {
+        assert_eq!(unmet(LimitRequirements::default().max_image_dimension_2d(16384)), [("max_image_dimension2_d", 16384, 8192)]);
+        assert_eq!(unmet(LimitRequirements::default().max_push_constants_size(256)), [("max_push_constants_size", 256, 128)]);
+        assert_eq!(unmet(LimitRequirements::default().max_bound_descriptor_sets(32)), [("max_bound_descriptor_sets", 32, 8)]);
+        assert_eq!(unmet(LimitRequirements::default().max_compute_work_group_invocations(2048)), [("max_compute_work_group_invocations", 2048, 1024)]);
+        assert_eq!(unmet(LimitRequirements::default().max_compute_work_group_size([2048, 1024, 128])), [
+            ("max_compute_work_group_size[0]", 2048, 1024),
+            ("max_compute_work_group_size[2]", 128, 64),
+        ]);
+    }
+}
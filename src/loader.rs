@@ -0,0 +1,129 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use ash::vk;
+use crate::diagnostics::Diagnostics;
+use crate::error::{Error, Result};
+use crate::util::format_api_version;
+
+/// Where the Vulkan loader library was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoaderSource {
+    /// The system loader found by `ash::Entry::load`.
+    System,
+    /// An explicit library path.
+    Path(PathBuf),
+}
+
+/// How drivers given to `InstanceBuilder::driver` are combined with the drivers the loader finds by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverLoadingMode {
+    /// Only the given drivers are used.
+    Exclusive,
+    /// The given drivers are used in addition to the drivers found by the loader.
+    Inclusive,
+}
+
+impl DriverLoadingMode {
+    fn to_vk(self) -> vk::DirectDriverLoadingModeLUNARG {
+        match self {
+            DriverLoadingMode::Exclusive => vk::DirectDriverLoadingModeLUNARG::EXCLUSIVE,
+            DriverLoadingMode::Inclusive => vk::DirectDriverLoadingModeLUNARG::INCLUSIVE,
+        }
+    }
+}
+
+/// Which loader and drivers an instance was created with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoaderInfo {
+    pub source: LoaderSource,
+    /// The highest instance version supported by the loader.
+    pub version: u32,
+    /// Drivers loaded directly through `VK_LUNARG_direct_driver_loading`.
+    pub drivers: Vec<PathBuf>,
+    /// `None` when no driver is loaded directly.
+    pub driver_mode: Option<DriverLoadingMode>,
+}
+
+impl Display for LoaderInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            LoaderSource::System => write!(f, "system Vulkan loader")?,
+            LoaderSource::Path(path) => write!(f, "Vulkan loader {}", path.display())?,
+        }
+        write!(f, " (instance version {})", format_api_version(self.version))?;
+
+        if let Some(driver_mode) = self.driver_mode {
+            let drivers: Vec<String> = self.drivers.iter().map(|driver| driver.display().to_string()).collect();
+            write!(f, ", {:?} drivers: {}", driver_mode, drivers.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl LoaderSource {
+    /// # Safety
+    /// Loading a library runs its initialisation code.
+    pub(crate) unsafe fn load(&self) -> Result<ash::Entry> {
+        let entry = match self {
+            LoaderSource::System => ash::Entry::load(),
+            LoaderSource::Path(path) => ash::Entry::load_from(path),
+        };
+
+        entry.map_err(|error| Error::Loading {
+            loader: self.to_string(),
+            error,
+            diagnostics: Box::new(Diagnostics::collect()),
+        })
+    }
+}
+
+impl Display for LoaderSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoaderSource::System => write!(f, "system loader"),
+            LoaderSource::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A driver library loaded directly, to be given to the loader through `VK_LUNARG_direct_driver_loading`.
+pub(crate) struct DirectDriver {
+    pub path: PathBuf,
+    // Keeps the driver loaded for as long as the instance using it.
+    _library: libloading::Library,
+    get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddrLUNARG,
+}
+
+impl DirectDriver {
+    /// # Safety
+    /// Loading a library runs its initialisation code.
+    pub unsafe fn load(path: &Path) -> Result<Self> {
+        let driver_error = |reason: String| Error::DriverLoading {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let library = libloading::Library::new(path).map_err(|error| driver_error(error.to_string()))?;
+        let get_instance_proc_addr = *library.get::<unsafe extern "system" fn(vk::Instance, *const std::ffi::c_char) -> vk::PFN_vkVoidFunction>(b"vk_icdGetInstanceProcAddr\0")
+            .map_err(|error| driver_error(error.to_string()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            _library: library,
+            get_instance_proc_addr: Some(get_instance_proc_addr),
+        })
+    }
+
+    pub fn loading_info(&self) -> vk::DirectDriverLoadingInfoLUNARG {
+        vk::DirectDriverLoadingInfoLUNARG::builder()
+            .pfn_get_instance_proc_addr(self.get_instance_proc_addr)
+            .build()
+    }
+}
+
+pub(crate) fn driver_loading_list(mode: DriverLoadingMode, drivers: &[vk::DirectDriverLoadingInfoLUNARG]) -> vk::DirectDriverLoadingListLUNARG {
+    vk::DirectDriverLoadingListLUNARG::builder()
+        .mode(mode.to_vk())
+        .drivers(drivers)
+        .build()
+}
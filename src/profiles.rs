@@ -0,0 +1,1058 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::CString;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use ash::vk;
+use serde_json::{Map, Value};
+use crate::backend::ExtendedProperties;
+use crate::error::{Error, Result};
+use crate::features::DeviceFeatures;
+use crate::gpu::PhysicalDevice;
+use crate::info::{core_formats, limit_table, LimitValue};
+use crate::util::format_api_version;
+
+/// The value of a device property, in a form that can be compared with the JSON value of a profile.
+#[derive(Debug)]
+enum PropertyValue {
+    Number(f64),
+    Numbers(Vec<f64>),
+    /// The ash names of the bits set, `prefix` being the prefix of the Vulkan names, e.g. `VK_SAMPLE_COUNT_`.
+    Flags {
+        prefix: &'static str,
+        names: Vec<String>,
+    },
+    Enum {
+        prefix: &'static str,
+        name: String,
+    },
+}
+
+trait IntoPropertyValue {
+    fn into_property_value(self) -> PropertyValue;
+}
+
+impl IntoPropertyValue for u32 {
+    fn into_property_value(self) -> PropertyValue {
+        PropertyValue::Number(self as f64)
+    }
+}
+
+impl IntoPropertyValue for u64 {
+    fn into_property_value(self) -> PropertyValue {
+        PropertyValue::Number(self as f64)
+    }
+}
+
+fn flag_names<F: Debug>(raw: u32, from_raw: fn(u32) -> F) -> Vec<String> {
+    (0..32).map(|bit| 1 << bit)
+        .filter(|bit| raw & bit != 0)
+        .map(|bit| format!("{:?}", from_raw(bit)))
+        .collect()
+}
+
+macro_rules! flags_property {
+    ($($ty:ty => $prefix:literal),* $(,)?) => {
+        $(impl IntoPropertyValue for $ty {
+            fn into_property_value(self) -> PropertyValue {
+                PropertyValue::Flags {
+                    prefix: $prefix,
+                    names: flag_names(self.as_raw(), <$ty>::from_raw),
+                }
+            }
+        })*
+    };
+}
+
+macro_rules! enum_property {
+    ($($ty:ty => $prefix:literal),* $(,)?) => {
+        $(impl IntoPropertyValue for $ty {
+            fn into_property_value(self) -> PropertyValue {
+                PropertyValue::Enum {
+                    prefix: $prefix,
+                    name: format!("{:?}", self),
+                }
+            }
+        })*
+    };
+}
+
+flags_property!(
+    vk::SampleCountFlags => "VK_SAMPLE_COUNT_",
+    vk::ShaderStageFlags => "VK_SHADER_STAGE_",
+    vk::SubgroupFeatureFlags => "VK_SUBGROUP_FEATURE_",
+    vk::ResolveModeFlags => "VK_RESOLVE_MODE_",
+);
+
+enum_property!(
+    vk::PointClippingBehavior => "VK_POINT_CLIPPING_BEHAVIOR_",
+    vk::ShaderFloatControlsIndependence => "VK_SHADER_FLOAT_CONTROLS_INDEPENDENCE_",
+    vk::DriverId => "VK_DRIVER_ID_",
+);
+
+impl From<&LimitValue> for PropertyValue {
+    fn from(value: &LimitValue) -> Self {
+        match value {
+            LimitValue::Unsigned(value) => PropertyValue::Number(*value as f64),
+            LimitValue::Signed(value) => PropertyValue::Number(*value as f64),
+            LimitValue::Float(value) => PropertyValue::Number(*value as f64),
+            LimitValue::Unsigneds(values) => PropertyValue::Numbers(values.iter().map(|value| *value as f64).collect()),
+            LimitValue::Floats(values) => PropertyValue::Numbers(values.iter().map(|value| *value as f64).collect()),
+            LimitValue::SampleCounts(counts) => counts.into_property_value(),
+        }
+    }
+}
+
+// Generates, for one property structure, a module listing its property names and reading a property from its name.
+macro_rules! property_table {
+    ($module:ident, $ty:ty, [$($field:ident),* $(,)?]) => {
+        mod $module {
+            use ash::vk;
+            use super::{IntoPropertyValue, PropertyValue};
+
+            pub const NAMES: &[&str] = &[$(stringify!($field)),*];
+
+            pub fn get(properties: &$ty, name: &str) -> Option<PropertyValue> {
+                match name {
+                    $(stringify!($field) => Some(properties.$field.into_property_value()),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+property_table!(sparse_properties, vk::PhysicalDeviceSparseProperties, [
+    residency_standard2_d_block_shape, residency_standard2_d_multisample_block_shape,
+    residency_standard3_d_block_shape, residency_aligned_mip_size, residency_non_resident_strict
+]);
+
+property_table!(vulkan_1_1, vk::PhysicalDeviceVulkan11Properties, [
+    subgroup_size, subgroup_supported_stages, subgroup_supported_operations, subgroup_quad_operations_in_all_stages,
+    point_clipping_behavior, max_multiview_view_count, max_multiview_instance_index, protected_no_fault,
+    max_per_set_descriptors, max_memory_allocation_size
+]);
+
+property_table!(vulkan_1_2, vk::PhysicalDeviceVulkan12Properties, [
+    driver_id, denorm_behavior_independence, rounding_mode_independence,
+    shader_signed_zero_inf_nan_preserve_float16, shader_signed_zero_inf_nan_preserve_float32,
+    shader_signed_zero_inf_nan_preserve_float64, shader_denorm_preserve_float16, shader_denorm_preserve_float32,
+    shader_denorm_preserve_float64, shader_denorm_flush_to_zero_float16, shader_denorm_flush_to_zero_float32,
+    shader_denorm_flush_to_zero_float64, shader_rounding_mode_rte_float16, shader_rounding_mode_rte_float32,
+    shader_rounding_mode_rte_float64, shader_rounding_mode_rtz_float16, shader_rounding_mode_rtz_float32,
+    shader_rounding_mode_rtz_float64, max_update_after_bind_descriptors_in_all_pools,
+    shader_uniform_buffer_array_non_uniform_indexing_native, shader_sampled_image_array_non_uniform_indexing_native,
+    shader_storage_buffer_array_non_uniform_indexing_native, shader_storage_image_array_non_uniform_indexing_native,
+    shader_input_attachment_array_non_uniform_indexing_native, robust_buffer_access_update_after_bind,
+    quad_divergent_implicit_lod, max_per_stage_descriptor_update_after_bind_samplers,
+    max_per_stage_descriptor_update_after_bind_uniform_buffers, max_per_stage_descriptor_update_after_bind_storage_buffers,
+    max_per_stage_descriptor_update_after_bind_sampled_images, max_per_stage_descriptor_update_after_bind_storage_images,
+    max_per_stage_descriptor_update_after_bind_input_attachments, max_per_stage_update_after_bind_resources,
+    max_descriptor_set_update_after_bind_samplers, max_descriptor_set_update_after_bind_uniform_buffers,
+    max_descriptor_set_update_after_bind_uniform_buffers_dynamic, max_descriptor_set_update_after_bind_storage_buffers,
+    max_descriptor_set_update_after_bind_storage_buffers_dynamic, max_descriptor_set_update_after_bind_sampled_images,
+    max_descriptor_set_update_after_bind_storage_images, max_descriptor_set_update_after_bind_input_attachments,
+    supported_depth_resolve_modes, supported_stencil_resolve_modes, independent_resolve_none, independent_resolve,
+    filter_minmax_single_component_formats, filter_minmax_image_component_mapping,
+    max_timeline_semaphore_value_difference, framebuffer_integer_color_sample_counts
+]);
+
+property_table!(vulkan_1_3, vk::PhysicalDeviceVulkan13Properties, [
+    min_subgroup_size, max_subgroup_size, max_compute_workgroup_subgroups, required_subgroup_size_stages,
+    max_inline_uniform_block_size, max_per_stage_descriptor_inline_uniform_blocks,
+    max_per_stage_descriptor_update_after_bind_inline_uniform_blocks, max_descriptor_set_inline_uniform_blocks,
+    max_descriptor_set_update_after_bind_inline_uniform_blocks, max_inline_uniform_total_size,
+    integer_dot_product8_bit_unsigned_accelerated, integer_dot_product8_bit_signed_accelerated,
+    integer_dot_product8_bit_mixed_signedness_accelerated, integer_dot_product4x8_bit_packed_unsigned_accelerated,
+    integer_dot_product4x8_bit_packed_signed_accelerated, integer_dot_product4x8_bit_packed_mixed_signedness_accelerated,
+    integer_dot_product16_bit_unsigned_accelerated, integer_dot_product16_bit_signed_accelerated,
+    integer_dot_product16_bit_mixed_signedness_accelerated, integer_dot_product32_bit_unsigned_accelerated,
+    integer_dot_product32_bit_signed_accelerated, integer_dot_product32_bit_mixed_signedness_accelerated,
+    integer_dot_product64_bit_unsigned_accelerated, integer_dot_product64_bit_signed_accelerated,
+    integer_dot_product64_bit_mixed_signedness_accelerated,
+    integer_dot_product_accumulating_saturating8_bit_unsigned_accelerated,
+    integer_dot_product_accumulating_saturating8_bit_signed_accelerated,
+    integer_dot_product_accumulating_saturating8_bit_mixed_signedness_accelerated,
+    integer_dot_product_accumulating_saturating4x8_bit_packed_unsigned_accelerated,
+    integer_dot_product_accumulating_saturating4x8_bit_packed_signed_accelerated,
+    integer_dot_product_accumulating_saturating4x8_bit_packed_mixed_signedness_accelerated,
+    integer_dot_product_accumulating_saturating16_bit_unsigned_accelerated,
+    integer_dot_product_accumulating_saturating16_bit_signed_accelerated,
+    integer_dot_product_accumulating_saturating16_bit_mixed_signedness_accelerated,
+    integer_dot_product_accumulating_saturating32_bit_unsigned_accelerated,
+    integer_dot_product_accumulating_saturating32_bit_signed_accelerated,
+    integer_dot_product_accumulating_saturating32_bit_mixed_signedness_accelerated,
+    integer_dot_product_accumulating_saturating64_bit_unsigned_accelerated,
+    integer_dot_product_accumulating_saturating64_bit_signed_accelerated,
+    integer_dot_product_accumulating_saturating64_bit_mixed_signedness_accelerated,
+    storage_texel_buffer_offset_alignment_bytes, storage_texel_buffer_offset_single_texel_alignment,
+    uniform_texel_buffer_offset_alignment_bytes, uniform_texel_buffer_offset_single_texel_alignment, max_buffer_size
+]);
+
+/// Convert a camel case member name of the Vulkan API to the snake case name used by ash, e.g. `maxImageDimension2D` to `max_image_dimension2_d`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let starts_word = chars.get(i + 1).is_some_and(|next| next.is_ascii_lowercase());
+            if previous.is_ascii_lowercase() || previous.is_ascii_digit() || (previous.is_ascii_uppercase() && starts_word) {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Convert a Vulkan enum or flag name to the name ash gives it, e.g. `VK_SAMPLE_COUNT_4_BIT` with the prefix `VK_SAMPLE_COUNT_` to `TYPE_4`.
+fn ash_name(name: &str, prefix: &str) -> Option<String> {
+    let mut words: Vec<&str> = name.strip_prefix(prefix)?.split('_').collect();
+    // `BIT` is the last word, or the one before a vendor suffix as in `_BIT_EXT`.
+    if let Some(position) = words.iter().rposition(|word| *word == "BIT") {
+        if position + 2 >= words.len() {
+            words.remove(position);
+        }
+    }
+
+    let name = words.join("_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Some(format!("TYPE_{}", name))
+    } else {
+        Some(name)
+    }
+}
+
+/// Whether an ash name matches a name converted with `ash_name`, which may carry the vendor suffix of the extension the value was promoted from.
+fn same_name(ash_name: &str, name: &str) -> bool {
+    ash_name.eq_ignore_ascii_case(name) || ["_KHR", "_EXT"].iter().any(|suffix| name.strip_suffix(suffix).is_some_and(|name| ash_name.eq_ignore_ascii_case(name)))
+}
+
+fn resolve_format(name: &str) -> Option<vk::Format> {
+    // Formats added by extensions, as ranges of raw values.
+    const EXTENSION_FORMATS: [(i32, i32); 5] = [
+        (1000054000, 1000054007),
+        (1000066000, 1000066013),
+        (1000156000, 1000156033),
+        (1000330000, 1000330003),
+        (1000340000, 1000340001),
+    ];
+
+    let name = ash_name(name, "VK_FORMAT_")?;
+    core_formats()
+        .chain(EXTENSION_FORMATS.iter().flat_map(|&(first, last)| first..=last).map(vk::Format::from_raw))
+        .find(|format| same_name(&format!("{:?}", format), &name))
+}
+
+fn resolve_format_feature(name: &str) -> Option<vk::FormatFeatureFlags> {
+    // The flags of `VkFormatProperties3` below bit 32 are the flags of `VkFormatProperties`.
+    let name = ash_name(name, "VK_FORMAT_FEATURE_2_").or_else(|| ash_name(name, "VK_FORMAT_FEATURE_"))?;
+    (0..32)
+        .map(|bit| vk::FormatFeatureFlags::from_raw(1 << bit))
+        .find(|flag| same_name(&format!("{:?}", flag), &name))
+}
+
+fn parse_api_version(version: &str) -> Option<u32> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(major)), Some(Some(minor)), patch, None) => Some(vk::make_api_version(0, major, minor, patch.flatten().unwrap_or(0))),
+        _ => None,
+    }
+}
+
+// Properties the device satisfies with a value at most the profile value, following the limit types of the specification.
+// Other numeric properties are satisfied with a value at least the profile value, including `min_memory_map_alignment`.
+const SMALLER_IS_BETTER: &[&str] = &[
+    "buffer_image_granularity",
+    "min_texel_offset",
+    "min_texel_gather_offset",
+    "min_interpolation_offset",
+    "min_texel_buffer_offset_alignment",
+    "min_uniform_buffer_offset_alignment",
+    "min_storage_buffer_offset_alignment",
+    "point_size_granularity",
+    "line_width_granularity",
+    "optimal_buffer_copy_offset_alignment",
+    "optimal_buffer_copy_row_pitch_alignment",
+    "non_coherent_atom_size",
+    "timestamp_period",
+    "min_subgroup_size",
+    "storage_texel_buffer_offset_alignment_bytes",
+    "uniform_texel_buffer_offset_alignment_bytes",
+];
+
+fn smaller_is_better(member: &str) -> bool {
+    SMALLER_IS_BETTER.contains(&member)
+}
+
+fn format_numbers(values: &[f64]) -> String {
+    format!("[{}]", values.iter().map(f64::to_string).collect::<Vec<_>>().join(", "))
+}
+
+/// Why the device value `actual` does not satisfy the profile value `required`, or `None` if it does.
+fn compare(member: &str, required: &Value, actual: &PropertyValue) -> Option<String> {
+    let at_least = |required: f64, actual: f64| if smaller_is_better(member) { actual <= required } else { actual >= required };
+
+    match (required, actual) {
+        (Value::Bool(required), PropertyValue::Number(actual)) => (*required && *actual == 0.0).then(|| "required but not supported".to_string()),
+        (Value::Number(required), PropertyValue::Number(actual)) => {
+            let required = required.as_f64()?;
+            let bound = if smaller_is_better(member) { "at most" } else { "at least" };
+            (!at_least(required, *actual)).then(|| format!("{} {} required, device has {}", bound, required, actual))
+        }
+        (Value::Array(required), PropertyValue::Numbers(actual)) => {
+            let Some(required) = required.iter().map(Value::as_f64).collect::<Option<Vec<f64>>>() else {
+                return Some(format!("cannot compare {} with the device value", Value::Array(required.clone())));
+            };
+
+            let met = if member.ends_with("_range") && required.len() == 2 && actual.len() == 2 {
+                actual[0] <= required[0] && actual[1] >= required[1]
+            } else {
+                required.len() == actual.len() && required.iter().zip(actual).all(|(required, actual)| at_least(*required, *actual))
+            };
+            (!met).then(|| format!("{} required, device has {}", format_numbers(&required), format_numbers(actual)))
+        }
+        (Value::Array(required), PropertyValue::Flags { prefix, names }) => {
+            let missing: Vec<&str> = required.iter()
+                .filter_map(Value::as_str)
+                .filter(|flag| !ash_name(flag, prefix).is_some_and(|flag| names.iter().any(|name| same_name(name, &flag))))
+                .collect();
+            (!missing.is_empty()).then(|| format!("missing {}", missing.join(", ")))
+        }
+        (Value::String(required), PropertyValue::Enum { prefix, name }) => {
+            let met = ash_name(required, prefix).is_some_and(|required| same_name(name, &required));
+            (!met).then(|| format!("{} required, device has {}{}", required, prefix, name))
+        }
+        (required, _) => Some(format!("cannot compare {} with the device value", required)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropertyGroup {
+    Limits,
+    SparseProperties,
+    Vulkan11,
+    Vulkan12,
+    Vulkan13,
+}
+
+impl PropertyGroup {
+    /// The group of a property structure, `None` for structures whose members are looked up in every core structure.
+    fn from_structure(structure: &str) -> Option<Self> {
+        match structure {
+            "VkPhysicalDeviceVulkan11Properties" => Some(PropertyGroup::Vulkan11),
+            "VkPhysicalDeviceVulkan12Properties" => Some(PropertyGroup::Vulkan12),
+            "VkPhysicalDeviceVulkan13Properties" => Some(PropertyGroup::Vulkan13),
+            _ => None,
+        }
+    }
+
+    fn contains(self, member: &str) -> bool {
+        match self {
+            PropertyGroup::Limits => limit_table(&vk::PhysicalDeviceLimits::default()).contains_key(member),
+            PropertyGroup::SparseProperties => sparse_properties::NAMES.contains(&member),
+            PropertyGroup::Vulkan11 => vulkan_1_1::NAMES.contains(&member),
+            PropertyGroup::Vulkan12 => vulkan_1_2::NAMES.contains(&member),
+            PropertyGroup::Vulkan13 => vulkan_1_3::NAMES.contains(&member),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FeatureRequirement {
+    /// The entry of the profile, e.g. `VkPhysicalDeviceVulkan12Features.descriptorIndexing`.
+    entry: String,
+    group: &'static str,
+    name: String,
+}
+
+#[derive(Debug, Clone)]
+struct PropertyRequirement {
+    entry: String,
+    group: PropertyGroup,
+    member: String,
+    value: Value,
+}
+
+#[derive(Debug, Clone)]
+struct FormatRequirement {
+    name: String,
+    format: vk::Format,
+    /// The required features of each member of `VkFormatProperties`, with their names in the profile.
+    features: BTreeMap<&'static str, Vec<(String, vk::FormatFeatureFlags)>>,
+}
+
+/// One named block of the `capabilities` of a profile file.
+#[derive(Debug, Clone, Default)]
+struct Capabilities {
+    name: String,
+    extensions: Vec<String>,
+    features: Vec<FeatureRequirement>,
+    properties: Vec<PropertyRequirement>,
+    formats: Vec<FormatRequirement>,
+    skipped: Vec<String>,
+}
+
+fn as_object<'a>(value: &'a Value, what: &str) -> std::result::Result<&'a Map<String, Value>, String> {
+    value.as_object().ok_or_else(|| format!("{} is not an object", what))
+}
+
+impl Capabilities {
+    fn parse(name: &str, value: &Value) -> std::result::Result<Self, String> {
+        let mut capabilities = Capabilities {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let value = as_object(value, &format!("capabilities {:?}", name))?;
+
+        if let Some(extensions) = value.get("extensions") {
+            capabilities.extensions = as_object(extensions, "extensions")?.keys().cloned().collect();
+        }
+
+        if let Some(features) = value.get("features") {
+            for (structure, members) in as_object(features, "features")? {
+                for (member, enabled) in as_object(members, structure)? {
+                    if enabled.as_bool() == Some(true) {
+                        capabilities.add_feature(structure, member);
+                    }
+                }
+            }
+        }
+
+        if let Some(properties) = value.get("properties") {
+            for (structure, members) in as_object(properties, "properties")? {
+                for (member, value) in as_object(members, structure)? {
+                    match (structure.as_str(), member.as_str()) {
+                        ("VkPhysicalDeviceProperties", "limits") => {
+                            for (limit, value) in as_object(value, "limits")? {
+                                capabilities.add_property(&format!("{}.limits", structure), Some(PropertyGroup::Limits), limit, value);
+                            }
+                        }
+                        ("VkPhysicalDeviceProperties", "sparseProperties") => {
+                            for (property, value) in as_object(value, "sparseProperties")? {
+                                capabilities.add_property(&format!("{}.sparseProperties", structure), Some(PropertyGroup::SparseProperties), property, value);
+                            }
+                        }
+                        ("VkPhysicalDeviceProperties", _) => capabilities.skip(format!("{}.{}", structure, member)),
+                        _ => capabilities.add_property(structure, PropertyGroup::from_structure(structure), member, value),
+                    }
+                }
+            }
+        }
+
+        if let Some(formats) = value.get("formats") {
+            for (format, structures) in as_object(formats, "formats")? {
+                capabilities.add_format(format, as_object(structures, format)?)?;
+            }
+        }
+
+        if value.contains_key("queueFamiliesProperties") {
+            capabilities.skip("queueFamiliesProperties".to_string());
+        }
+
+        Ok(capabilities)
+    }
+
+    fn skip(&mut self, entry: String) {
+        log::warn!("profile capabilities {}: {} cannot be checked and is ignored", self.name, entry);
+        self.skipped.push(entry);
+    }
+
+    fn add_feature(&mut self, structure: &str, member: &str) {
+        let entry = format!("{}.{}", structure, member);
+        let name = snake_case(member);
+        let group = match structure {
+            "VkPhysicalDeviceFeatures" => Some("vulkan_1_0"),
+            "VkPhysicalDeviceVulkan11Features" => Some("vulkan_1_1"),
+            "VkPhysicalDeviceVulkan12Features" => Some("vulkan_1_2"),
+            "VkPhysicalDeviceVulkan13Features" => Some("vulkan_1_3"),
+            // Features of promoted extension structures have the same name in the core structures.
+            _ => ["vulkan_1_0", "vulkan_1_1", "vulkan_1_2", "vulkan_1_3"].into_iter()
+                .find(|group| DeviceFeatures::default().get_mut(group, &name).is_some()),
+        };
+
+        match group {
+            Some(group) if DeviceFeatures::default().get_mut(group, &name).is_some() => self.features.push(FeatureRequirement {
+                entry,
+                group,
+                name,
+            }),
+            _ => self.skip(entry),
+        }
+    }
+
+    fn add_property(&mut self, structure: &str, group: Option<PropertyGroup>, member: &str, value: &Value) {
+        let entry = format!("{}.{}", structure, member);
+        let name = snake_case(member);
+        let group = group.or_else(|| {
+            [PropertyGroup::Vulkan11, PropertyGroup::Vulkan12, PropertyGroup::Vulkan13].into_iter()
+                .find(|group| group.contains(&name))
+        });
+
+        match group {
+            Some(group) if group.contains(&name) => self.properties.push(PropertyRequirement {
+                entry,
+                group,
+                member: name,
+                value: value.clone(),
+            }),
+            _ => self.skip(entry),
+        }
+    }
+
+    fn add_format(&mut self, name: &str, structures: &Map<String, Value>) -> std::result::Result<(), String> {
+        let Some(format) = resolve_format(name) else {
+            self.skip(name.to_string());
+            return Ok(());
+        };
+
+        let mut requirement = FormatRequirement {
+            name: name.to_string(),
+            format,
+            features: BTreeMap::new(),
+        };
+        for (structure, members) in structures {
+            if !["VkFormatProperties", "VkFormatProperties2", "VkFormatProperties3", "VkFormatProperties3KHR"].contains(&structure.as_str()) {
+                self.skip(format!("{}.{}", name, structure));
+                continue;
+            }
+
+            for (member, flags) in as_object(members, structure)? {
+                let member = match member.as_str() {
+                    "linearTilingFeatures" => "linearTilingFeatures",
+                    "optimalTilingFeatures" => "optimalTilingFeatures",
+                    "bufferFeatures" => "bufferFeatures",
+                    _ => {
+                        self.skip(format!("{}.{}.{}", name, structure, member));
+                        continue;
+                    }
+                };
+
+                let flags = flags.as_array().ok_or_else(|| format!("{}.{}.{} is not an array", name, structure, member))?;
+                for flag in flags {
+                    let flag = flag.as_str().ok_or_else(|| format!("{}.{}.{} contains a value that is not a string", name, structure, member))?;
+                    match resolve_format_feature(flag) {
+                        Some(bit) => requirement.features.entry(member).or_default().push((flag.to_string(), bit)),
+                        None => self.skip(format!("{}.{}.{}", name, member, flag)),
+                    }
+                }
+            }
+        }
+
+        self.formats.push(requirement);
+        Ok(())
+    }
+
+    fn missing(&self, device: &DeviceCapabilities) -> Vec<ProfileMiss> {
+        let mut missing = vec![];
+
+        for extension in &self.extensions {
+            if !device.supports_extension(extension) {
+                missing.push(ProfileMiss::new(extension, "extension not supported"));
+            }
+        }
+
+        let features = device.features.list();
+        for feature in &self.features {
+            let supported = features.iter().any(|(group, name, enabled)| *group == feature.group && *name == feature.name && *enabled);
+            if !supported {
+                missing.push(ProfileMiss::new(&feature.entry, "feature not supported"));
+            }
+        }
+
+        for property in &self.properties {
+            let reason = match device.property(property.group, &property.member) {
+                Some(actual) => compare(&property.member, &property.value, &actual),
+                None => Some("not reported by the device".to_string()),
+            };
+            if let Some(reason) = reason {
+                missing.push(ProfileMiss::new(&property.entry, &reason));
+            }
+        }
+
+        for format in &self.formats {
+            let properties = device.format_properties(format.format);
+            for (member, required) in &format.features {
+                let supported = match *member {
+                    "linearTilingFeatures" => properties.linear_tiling_features,
+                    "optimalTilingFeatures" => properties.optimal_tiling_features,
+                    _ => properties.buffer_features,
+                };
+                let unsupported: Vec<&str> = required.iter()
+                    .filter(|(_, flag)| !supported.contains(*flag))
+                    .map(|(name, _)| name.as_str())
+                    .collect();
+                if !unsupported.is_empty() {
+                    missing.push(ProfileMiss::new(&format!("{}.{}", format.name, member), &format!("missing {}", unsupported.join(", "))));
+                }
+            }
+        }
+
+        missing
+    }
+}
+
+/// What a device supports, read from the queries cached by `PhysicalDevice` to check every capability of a profile.
+struct DeviceCapabilities<'a> {
+    physical_device: &'a PhysicalDevice,
+    api_version: u32,
+    extensions: Option<&'a HashSet<CString>>,
+    features: DeviceFeatures,
+    extended: &'a ExtendedProperties,
+}
+
+impl<'a> DeviceCapabilities<'a> {
+    fn query(physical_device: &'a PhysicalDevice) -> Self {
+        Self {
+            physical_device,
+            api_version: physical_device.api_version(),
+            extensions: physical_device.supported_extensions().ok(),
+            features: physical_device.features(),
+            extended: physical_device.extended_properties(),
+        }
+    }
+
+    fn supports_extension(&self, extension: &str) -> bool {
+        self.extensions.is_some_and(|extensions| extensions.iter().any(|supported| supported.to_bytes() == extension.as_bytes()))
+    }
+
+    fn property(&self, group: PropertyGroup, member: &str) -> Option<PropertyValue> {
+        match group {
+            PropertyGroup::Limits => self.physical_device.info().limits.get(member).map(PropertyValue::from),
+            PropertyGroup::SparseProperties => sparse_properties::get(&self.extended.properties.sparse_properties, member),
+            PropertyGroup::Vulkan11 => vulkan_1_1::get(self.extended.vulkan_1_1.as_ref()?, member),
+            PropertyGroup::Vulkan12 => vulkan_1_2::get(self.extended.vulkan_1_2.as_ref()?, member),
+            PropertyGroup::Vulkan13 => vulkan_1_3::get(self.extended.vulkan_1_3.as_ref()?, member),
+        }
+    }
+
+    fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        self.physical_device.instance().backend().format_properties(self.physical_device.handle(), format)
+    }
+}
+
+/// A profile entry a device does not satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileMiss {
+    /// The entry as named in the profile file, e.g. `VkPhysicalDeviceProperties.limits.maxImageDimension2D`.
+    pub entry: String,
+    pub reason: String,
+}
+
+impl ProfileMiss {
+    fn new(entry: &str, reason: &str) -> Self {
+        Self {
+            entry: entry.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Display for ProfileMiss {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.entry, self.reason)
+    }
+}
+
+/// A Vulkan profile, as defined by a Khronos Vulkan Profiles JSON file such as `VP_KHR_roadmap_2022`.
+///
+/// Extensions, features, properties of `VkPhysicalDeviceProperties` and of the core 1.1 to 1.3 structures, and format features are checked.
+/// Entries that cannot be checked, like queue family properties or properties of structures that were not promoted to core, are logged and listed by `skipped`.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    label: String,
+    description: String,
+    version: u32,
+    api_version: u32,
+    /// Every entry lists alternatives, at least one of which must be supported.
+    capabilities: Vec<Vec<Capabilities>>,
+}
+
+impl Profile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The version of the profile itself.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The minimum Vulkan version of the profile.
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    fn required_capabilities(&self) -> impl Iterator<Item = &Capabilities> {
+        self.capabilities.iter().filter(|alternatives| alternatives.len() == 1).flatten()
+    }
+
+    /// The extensions the profile requires, leaving out capabilities with alternatives.
+    pub fn extensions(&self) -> Vec<CString> {
+        let mut extensions: Vec<CString> = vec![];
+        for extension in self.required_capabilities().flat_map(|capabilities| &capabilities.extensions) {
+            if let Ok(extension) = CString::new(extension.as_str()) {
+                if !extensions.contains(&extension) {
+                    extensions.push(extension);
+                }
+            }
+        }
+        extensions
+    }
+
+    /// The features the profile requires, leaving out capabilities with alternatives.
+    pub fn features(&self) -> DeviceFeatures {
+        let mut features = DeviceFeatures::default();
+        for feature in self.required_capabilities().flat_map(|capabilities| &capabilities.features) {
+            features.set(&format!("{}.{}", feature.group, feature.name), true);
+        }
+        features
+    }
+
+    /// Entries of the profile that are not checked.
+    pub fn skipped(&self) -> Vec<&str> {
+        self.capabilities.iter()
+            .flatten()
+            .flat_map(|capabilities| &capabilities.skipped)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Every entry of the profile the device does not satisfy. An empty list means the device supports the profile.
+    pub fn missing(&self, physical_device: &PhysicalDevice) -> Vec<ProfileMiss> {
+        let device = DeviceCapabilities::query(physical_device);
+        let mut missing = vec![];
+
+        if device.api_version < self.api_version {
+            missing.push(ProfileMiss::new("api-version", &format!("Vulkan {} required, device supports {}", format_api_version(self.api_version), format_api_version(device.api_version))));
+        }
+
+        for alternatives in &self.capabilities {
+            match alternatives.as_slice() {
+                [capabilities] => missing.extend(capabilities.missing(&device)),
+                _ => {
+                    if !alternatives.iter().any(|capabilities| capabilities.missing(&device).is_empty()) {
+                        let names: Vec<&str> = alternatives.iter().map(|capabilities| capabilities.name.as_str()).collect();
+                        missing.push(ProfileMiss::new(&format!("one of {}", names.join(", ")), "no alternative is supported"));
+                    }
+                }
+            }
+        }
+
+        missing
+    }
+
+    pub fn is_supported(&self, physical_device: &PhysicalDevice) -> bool {
+        self.missing(physical_device).is_empty()
+    }
+}
+
+/// The profiles of a Vulkan Profiles JSON file.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json).map_err(|error| Error::InvalidProfile {
+            path: None,
+            reason: error.to_string(),
+        })?;
+
+        Self::parse(&value).map_err(|reason| Error::InvalidProfile {
+            path: None,
+            reason,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|error| Error::InvalidProfile {
+            path: Some(path.to_path_buf()),
+            reason: error.to_string(),
+        })?;
+
+        Self::from_json(&json).map_err(|error| match error {
+            Error::InvalidProfile { reason, .. } => Error::InvalidProfile {
+                path: Some(path.to_path_buf()),
+                reason,
+            },
+            error => error,
+        })
+    }
+
+    fn parse(value: &Value) -> std::result::Result<Self, String> {
+        let value = as_object(value, "the profile file")?;
+        let capabilities = match value.get("capabilities") {
+            Some(capabilities) => as_object(capabilities, "capabilities")?.iter()
+                .map(|(name, value)| Ok((name.as_str(), Capabilities::parse(name, value)?)))
+                .collect::<std::result::Result<BTreeMap<&str, Capabilities>, String>>()?,
+            None => BTreeMap::new(),
+        };
+        let profiles = as_object(value.get("profiles").ok_or("there is no \"profiles\" object")?, "profiles")?;
+
+        let lookup = |name: &Value| -> std::result::Result<Capabilities, String> {
+            let name = name.as_str().ok_or_else(|| format!("capabilities reference {} is not a string", name))?;
+            capabilities.get(name).cloned().ok_or_else(|| format!("unknown capabilities {:?}", name))
+        };
+
+        let profiles = profiles.iter()
+            .map(|(name, profile)| {
+                let profile = as_object(profile, name)?;
+                let string = |key: &str| profile.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+                let api_version = match profile.get("api-version").and_then(Value::as_str) {
+                    Some(version) => parse_api_version(version).ok_or_else(|| format!("invalid api-version {:?} in profile {}", version, name))?,
+                    None => vk::API_VERSION_1_0,
+                };
+
+                let capabilities = profile.get("capabilities").and_then(Value::as_array)
+                    .ok_or_else(|| format!("profile {} has no \"capabilities\" array", name))?
+                    .iter()
+                    .map(|entry| match entry {
+                        Value::Array(alternatives) => alternatives.iter().map(lookup).collect(),
+                        entry => Ok(vec![lookup(entry)?]),
+                    })
+                    .collect::<std::result::Result<Vec<Vec<Capabilities>>, String>>()?;
+
+                Ok(Profile {
+                    name: name.clone(),
+                    label: string("label"),
+                    description: string("description"),
+                    version: profile.get("version").and_then(Value::as_u64).unwrap_or(1) as u32,
+                    api_version,
+                    capabilities,
+                })
+            })
+            .collect::<std::result::Result<Vec<Profile>, String>>()?;
+
+        Ok(Self {
+            profiles,
+        })
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// The profile with the given name.
+    pub fn get(&self, name: &str) -> Result<Profile> {
+        self.profiles.iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownProfile {
+                name: name.to_string(),
+                available: self.profiles.iter().map(|profile| profile.name.clone()).collect(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use ash::vk;
+    use crate::backend::fake::{instance, physical_device, FakeDevice};
+    use crate::gpu::{GpuSelectionParameters, PhysicalDevice};
+    use crate::report::RejectionReason;
+    use super::{snake_case, ProfileMiss, Profiles};
+
+    const PROFILE: &str = r#"{
+        "capabilities": {
+            "baseline": {
+                "extensions": { "VK_KHR_swapchain": 1 },
+                "features": {
+                    "VkPhysicalDeviceFeatures": { "samplerAnisotropy": true, "wideLines": false },
+                    "VkPhysicalDeviceDescriptorIndexingFeatures": { "runtimeDescriptorArray": true }
+                },
+                "properties": {
+                    "VkPhysicalDeviceProperties": {
+                        "limits": {
+                            "maxImageDimension2D": 8192,
+                            "minUniformBufferOffsetAlignment": 256,
+                            "maxComputeWorkGroupSize": [1024, 1024, 64],
+                            "framebufferColorSampleCounts": ["VK_SAMPLE_COUNT_1_BIT", "VK_SAMPLE_COUNT_4_BIT"]
+                        }
+                    }
+                },
+                "formats": {
+                    "VK_FORMAT_R8G8B8A8_UNORM": {
+                        "VkFormatProperties": {
+                            "optimalTilingFeatures": ["VK_FORMAT_FEATURE_SAMPLED_IMAGE_BIT", "VK_FORMAT_FEATURE_COLOR_ATTACHMENT_BIT"]
+                        }
+                    }
+                },
+                "queueFamiliesProperties": []
+            },
+            "bc": { "features": { "VkPhysicalDeviceFeatures": { "textureCompressionBC": true } } },
+            "etc2": { "features": { "VkPhysicalDeviceFeatures": { "textureCompressionETC2": true } } }
+        },
+        "profiles": {
+            "VP_TEST_baseline": {
+                "version": 2,
+                "api-version": "1.2.198",
+                "label": "Test baseline",
+                "description": "Used by the tests",
+                "capabilities": ["baseline", ["bc", "etc2"]]
+            }
+        }
+    }"#;
+
+    fn supported_device(name: &str) -> FakeDevice {
+        FakeDevice::new(name, vk::PhysicalDeviceType::DISCRETE_GPU)
+            .feature("sampler_anisotropy")
+            .feature("runtime_descriptor_array")
+            .feature("texture_compression_etc2")
+            .limits(|limits| {
+                limits.max_image_dimension2_d = 16384;
+                limits.min_uniform_buffer_offset_alignment = 64;
+                limits.max_compute_work_group_size = [1024, 1024, 1024];
+                limits.framebuffer_color_sample_counts = vk::SampleCountFlags::TYPE_1 | vk::SampleCountFlags::TYPE_4 | vk::SampleCountFlags::TYPE_8;
+            })
+            .format(vk::Format::R8G8B8A8_UNORM, vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::COLOR_ATTACHMENT)
+    }
+
+    fn missing(device: FakeDevice) -> Vec<ProfileMiss> {
+        let profile = Profiles::from_json(PROFILE).unwrap().get("VP_TEST_baseline").unwrap();
+        let physical_device = physical_device(device);
+        profile.missing(&physical_device)
+    }
+
+    fn entries(missing: &[ProfileMiss]) -> Vec<&str> {
+        missing.iter().map(|miss| miss.entry.as_str()).collect()
+    }
+
+    #[test]
+    fn converts_member_names() {
+        assert_eq!(snake_case("maxImageDimension2D"), "max_image_dimension2_d");
+        assert_eq!(snake_case("storageBuffer16BitAccess"), "storage_buffer16_bit_access");
+        assert_eq!(snake_case("textureCompressionASTC_LDR"), "texture_compression_astc_ldr");
+        assert_eq!(snake_case("shaderRoundingModeRTEFloat16"), "shader_rounding_mode_rte_float16");
+        assert_eq!(snake_case("integerDotProduct4x8BitPackedSignedAccelerated"), "integer_dot_product4x8_bit_packed_signed_accelerated");
+    }
+
+    #[test]
+    fn parses_profile() {
+        let profiles = Profiles::from_json(PROFILE).unwrap();
+        let profile = profiles.get("VP_TEST_baseline").unwrap();
+        assert_eq!(profile.version(), 2);
+        assert_eq!(profile.api_version(), vk::make_api_version(0, 1, 2, 198));
+        assert_eq!(profile.label(), "Test baseline");
+        assert_eq!(profile.extensions(), [CString::new("VK_KHR_swapchain").unwrap()]);
+        assert_eq!(profile.features().enabled_names(), ["vulkan_1_0.sampler_anisotropy", "vulkan_1_2.runtime_descriptor_array"]);
+        assert_eq!(profile.skipped(), ["queueFamiliesProperties"]);
+        assert!(profiles.get("VP_TEST_missing").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(Profiles::from_json("{").is_err());
+        assert!(Profiles::from_json(r#"{ "capabilities": {} }"#).is_err());
+        assert!(Profiles::from_json(r#"{ "profiles": { "VP_TEST": { "capabilities": ["unknown"] } } }"#).is_err());
+    }
+
+    #[test]
+    fn supported_device_has_no_miss() {
+        assert_eq!(missing(supported_device("supported")), []);
+    }
+
+    #[test]
+    fn reports_each_missing_entry() {
+        let device = supported_device("unsupported")
+            .api_version(vk::API_VERSION_1_1)
+            .without_extension(ash::extensions::khr::Swapchain::name())
+            .limits(|limits| {
+                limits.max_image_dimension2_d = 4096;
+                limits.min_uniform_buffer_offset_alignment = 512;
+                limits.framebuffer_color_sample_counts = vk::SampleCountFlags::TYPE_1;
+            })
+            .format(vk::Format::R8G8B8A8_UNORM, vk::FormatFeatureFlags::SAMPLED_IMAGE);
+
+        let missing = missing(device);
+        assert_eq!(entries(&missing), [
+            "api-version",
+            "VK_KHR_swapchain",
+            "VkPhysicalDeviceDescriptorIndexingFeatures.runtimeDescriptorArray",
+            "VkPhysicalDeviceProperties.limits.framebufferColorSampleCounts",
+            "VkPhysicalDeviceProperties.limits.maxImageDimension2D",
+            "VkPhysicalDeviceProperties.limits.minUniformBufferOffsetAlignment",
+            "VK_FORMAT_R8G8B8A8_UNORM.optimalTilingFeatures",
+        ]);
+        assert_eq!(missing[3].reason, "missing VK_SAMPLE_COUNT_4_BIT");
+        assert_eq!(missing[4].reason, "at least 8192 required, device has 4096");
+        assert_eq!(missing[5].reason, "at most 256 required, device has 512");
+        assert_eq!(missing[6].reason, "missing VK_FORMAT_FEATURE_COLOR_ATTACHMENT_BIT");
+    }
+
+    #[test]
+    fn compares_limits_by_limit_type() {
+        let profiles = Profiles::from_json(r#"{
+            "capabilities": {
+                "limits": {
+                    "properties": {
+                        "VkPhysicalDeviceProperties": {
+                            "limits": { "minMemoryMapAlignment": 4096, "minTexelOffset": -8, "timestampPeriod": 1 }
+                        }
+                    }
+                }
+            },
+            "profiles": { "VP_TEST_limits": { "version": 1, "api-version": "1.0.0", "capabilities": ["limits"] } }
+        }"#).unwrap();
+        let profile = profiles.get("VP_TEST_limits").unwrap();
+        let missing = |device: FakeDevice| profile.missing(&physical_device(device));
+
+        let supported = FakeDevice::new("supported", vk::PhysicalDeviceType::DISCRETE_GPU).limits(|limits| {
+            limits.min_memory_map_alignment = 65536;
+            limits.min_texel_offset = -16;
+            limits.timestamp_period = 0.5;
+        });
+        assert_eq!(missing(supported), []);
+
+        let unsupported = FakeDevice::new("unsupported", vk::PhysicalDeviceType::DISCRETE_GPU).limits(|limits| {
+            limits.min_memory_map_alignment = 64;
+            limits.min_texel_offset = -4;
+            limits.timestamp_period = 2.0;
+        });
+        let missing = missing(unsupported);
+        assert_eq!(entries(&missing), [
+            "VkPhysicalDeviceProperties.limits.minMemoryMapAlignment",
+            "VkPhysicalDeviceProperties.limits.minTexelOffset",
+            "VkPhysicalDeviceProperties.limits.timestampPeriod",
+        ]);
+        assert_eq!(missing[0].reason, "at least 4096 required, device has 64");
+        assert_eq!(missing[1].reason, "at most -8 required, device has -4");
+        assert_eq!(missing[2].reason, "at most 1 required, device has 2");
+    }
+
+    #[test]
+    fn requires_one_alternative() {
+        let mut device = supported_device("no compression");
+        device.features.vulkan_1_0.texture_compression_etc2 = vk::FALSE;
+        assert_eq!(entries(&missing(device)), ["one of bc, etc2"]);
+
+        let device = supported_device("bc").feature("texture_compression_bc");
+        assert_eq!(missing(device), []);
+    }
+
+    #[test]
+    fn selection_rejects_devices_missing_the_profile() {
+        let profile = Profiles::from_json(PROFILE).unwrap().get("VP_TEST_baseline").unwrap();
+        let devices = vec![
+            FakeDevice::new("plain", vk::PhysicalDeviceType::DISCRETE_GPU),
+            supported_device("supported"),
+        ];
+        let parameters = GpuSelectionParameters::new().env_override(false).require_profile(profile);
+
+        let report = PhysicalDevice::evaluate(instance(devices), &parameters).unwrap();
+        assert!(matches!(&report.devices[0].rejections[..], [RejectionReason::ProfileNotMet { profile, .. }] if profile == "VP_TEST_baseline"));
+        assert!(report.devices[1].is_compatible());
+    }
+}
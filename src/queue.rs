@@ -0,0 +1,295 @@
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
+use ash::vk;
+use crate::debug::set_object_name;
+use crate::device::Device;
+use crate::error::{Error, Result};
+use crate::gpu::PhysicalDevice;
+use crate::surface::Surface;
+
+/// A queue family of a physical device.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFamily {
+    pub index: u32,
+    pub properties: vk::QueueFamilyProperties,
+}
+
+impl QueueFamily {
+    pub fn flags(&self) -> vk::QueueFlags {
+        self.properties.queue_flags
+    }
+
+    pub fn queue_count(&self) -> u32 {
+        self.properties.queue_count
+    }
+
+    pub fn supports_graphics(&self) -> bool {
+        self.flags().contains(vk::QueueFlags::GRAPHICS)
+    }
+
+    pub fn supports_compute(&self) -> bool {
+        self.flags().contains(vk::QueueFlags::COMPUTE)
+    }
+
+    /// Graphics and compute queues always support transfer operations, even when they don't report the transfer flag.
+    pub fn supports_transfer(&self) -> bool {
+        self.flags().intersects(vk::QueueFlags::TRANSFER | vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+    }
+
+    /// A compute family without graphics support, used for async compute.
+    pub fn is_dedicated_compute(&self) -> bool {
+        self.supports_compute() && !self.supports_graphics()
+    }
+
+    /// A family that only supports transfer (and possibly sparse binding), usually backed by a DMA engine.
+    pub fn is_dedicated_transfer(&self) -> bool {
+        self.flags().contains(vk::QueueFlags::TRANSFER) && !self.supports_graphics() && !self.supports_compute()
+    }
+}
+
+/// The queue families chosen for each kind of work. Several kinds can share the same family.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueFamilyIndices {
+    pub graphics: Option<u32>,
+    pub compute: Option<u32>,
+    pub transfer: Option<u32>,
+    /// Only resolved when a surface is given.
+    pub present: Option<u32>,
+}
+
+impl QueueFamilyIndices {
+    /// Pick the families to use from the queue families of a device.
+    ///
+    /// Compute prefers a family without graphics support and transfer prefers a transfer only family, so that work can run asynchronously from the graphics queue.
+    /// `can_present` tells whether a family can present to the target surface, presentation prefers the graphics family when it can present.
+    /// Fails with `Error::NoGraphicsQueue` when no family supports graphics.
+    pub fn resolve(families: &[QueueFamily], can_present: Option<&dyn Fn(u32) -> Result<bool>>) -> Result<Self> {
+        let usable = || families.iter().filter(|family| family.queue_count() > 0);
+
+        let mut presenting = BTreeSet::new();
+        if let Some(can_present) = can_present {
+            for family in usable() {
+                if can_present(family.index)? {
+                    presenting.insert(family.index);
+                }
+            }
+        }
+
+        let graphics = usable().filter(|family| family.supports_graphics())
+            .max_by_key(|family| (presenting.contains(&family.index), std::cmp::Reverse(family.index)))
+            .map(|family| family.index);
+        if graphics.is_none() {
+            return Err(Error::NoGraphicsQueue);
+        }
+
+        let compute = usable().find(|family| family.is_dedicated_compute())
+            .or_else(|| usable().find(|family| family.supports_compute()))
+            .map(|family| family.index);
+
+        let transfer = usable().find(|family| family.is_dedicated_transfer())
+            .or_else(|| usable().find(|family| family.is_dedicated_compute()))
+            .or_else(|| usable().find(|family| family.supports_transfer()))
+            .map(|family| family.index);
+
+        let present = match graphics {
+            Some(graphics) if presenting.contains(&graphics) => Some(graphics),
+            _ => presenting.iter().next().copied(),
+        };
+
+        Ok(Self {
+            graphics,
+            compute,
+            transfer,
+            present,
+        })
+    }
+
+    /// Every distinct family index in use, in ascending order.
+    pub fn unique(&self) -> Vec<u32> {
+        [self.graphics, self.compute, self.transfer, self.present].into_iter()
+            .flatten()
+            .collect::<BTreeSet<u32>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+impl PhysicalDevice {
+    pub fn queue_families(&self) -> Vec<QueueFamily> {
+        self.instance().backend().queue_families(self.handle()).into_iter()
+            .enumerate()
+            .map(|(index, properties)| QueueFamily {
+                index: index as u32,
+                properties,
+            })
+            .collect()
+    }
+
+    /// Resolve the queue families to use for graphics, compute, transfer and, if a surface is given, presentation.
+    pub fn resolve_queue_families(&self, surface: Option<&Surface>) -> Result<QueueFamilyIndices> {
+        let families = self.queue_families();
+        match surface {
+            Some(surface) => QueueFamilyIndices::resolve(&families, Some(&|family| surface.supports_present(self, family))),
+            None => QueueFamilyIndices::resolve(&families, None),
+        }
+    }
+}
+
+/// A queue retrieved from a logical device.
+///
+/// Vulkan requires queue operations to be externally synchronized, so the handle is guarded by a lock that must be held while submitting, presenting or waiting on the queue.
+#[derive(Debug)]
+pub struct Queue {
+    handle: vk::Queue,
+    family_index: u32,
+    index: u32,
+    lock: Mutex<()>,
+}
+
+/// Exclusive access to a queue, obtained with `Queue::lock`. The queue is unlocked when the guard is dropped.
+pub struct QueueGuard<'a> {
+    handle: vk::Queue,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl Deref for QueueGuard<'_> {
+    type Target = vk::Queue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl Queue {
+    pub(crate) fn new(handle: vk::Queue, family_index: u32, index: u32) -> Self {
+        Self {
+            handle,
+            family_index,
+            index,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The raw handle, without synchronization. Use `lock` to submit to or present on the queue.
+    pub fn handle(&self) -> vk::Queue {
+        self.handle
+    }
+
+    /// Lock the queue for exclusive use, blocking until no other thread holds it.
+    pub fn lock(&self) -> QueueGuard<'_> {
+        QueueGuard {
+            handle: self.handle,
+            // The lock guards no data, so a panic while holding it leaves nothing inconsistent.
+            _guard: self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+        }
+    }
+
+    /// Submit work to the queue while holding its lock.
+    ///
+    /// # Safety
+    /// `device` must be the device the queue was retrieved from, and `submits` and `fence` must satisfy the valid usage of `vkQueueSubmit`.
+    pub unsafe fn submit(&self, device: &ash::Device, submits: &[vk::SubmitInfo], fence: vk::Fence) -> Result<()> {
+        let queue = self.lock();
+        device.queue_submit(*queue, submits, fence).map_err(Error::vulkan("vkQueueSubmit"))
+    }
+
+    /// Wait for the queue to be idle while holding its lock.
+    ///
+    /// # Safety
+    /// `device` must be the device the queue was retrieved from.
+    pub unsafe fn wait_idle(&self, device: &ash::Device) -> Result<()> {
+        let queue = self.lock();
+        device.queue_wait_idle(*queue).map_err(Error::vulkan("vkQueueWaitIdle"))
+    }
+
+    /// Name the queue for validation messages and debugging tools. Does nothing without `VK_EXT_debug_utils`.
+    pub fn set_debug_name(&self, device: &Device, name: &str) -> Result<()> {
+        set_object_name(device.physical_device().instance().debug_utils(), device.handle().handle(), *self.lock(), name)
+    }
+
+    pub fn family_index(&self) -> u32 {
+        self.family_index
+    }
+
+    /// Index of the queue within its family.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+    use crate::backend::fake::{physical_device, FakeDevice};
+    use crate::error::{Error, Result};
+    use super::{QueueFamily, QueueFamilyIndices};
+
+    const GRAPHICS: vk::QueueFlags = vk::QueueFlags::from_raw(vk::QueueFlags::GRAPHICS.as_raw() | vk::QueueFlags::COMPUTE.as_raw() | vk::QueueFlags::TRANSFER.as_raw());
+    const COMPUTE: vk::QueueFlags = vk::QueueFlags::from_raw(vk::QueueFlags::COMPUTE.as_raw() | vk::QueueFlags::TRANSFER.as_raw());
+    const TRANSFER: vk::QueueFlags = vk::QueueFlags::TRANSFER;
+
+    fn families(queue_families: &[(vk::QueueFlags, u32)]) -> Vec<QueueFamily> {
+        physical_device(FakeDevice::new("device", vk::PhysicalDeviceType::DISCRETE_GPU).queue_families(queue_families)).queue_families()
+    }
+
+    fn resolve(queue_families: &[(vk::QueueFlags, u32)], presenting: &[u32]) -> Result<QueueFamilyIndices> {
+        QueueFamilyIndices::resolve(&families(queue_families), Some(&|family| Ok(presenting.contains(&family))))
+    }
+
+    #[test]
+    fn graphics_prefers_a_presenting_family() {
+        let queue_families = [(GRAPHICS, 1), (GRAPHICS, 1), (GRAPHICS, 1)];
+        assert_eq!(resolve(&queue_families, &[2]).unwrap().graphics, Some(2));
+        assert_eq!(resolve(&queue_families, &[1, 2]).unwrap().graphics, Some(1));
+        assert_eq!(resolve(&queue_families, &[]).unwrap().graphics, Some(0));
+    }
+
+    #[test]
+    fn families_without_queues_are_ignored() {
+        let indices = resolve(&[(GRAPHICS, 0), (GRAPHICS, 1), (COMPUTE, 0)], &[0, 1]).unwrap();
+        assert_eq!(indices.graphics, Some(1));
+        assert_eq!(indices.compute, Some(1));
+        assert_eq!(indices.present, Some(1));
+    }
+
+    #[test]
+    fn compute_prefers_a_dedicated_family() {
+        assert_eq!(resolve(&[(GRAPHICS, 1), (COMPUTE, 2)], &[]).unwrap().compute, Some(1));
+        assert_eq!(resolve(&[(GRAPHICS, 1), (TRANSFER, 1)], &[]).unwrap().compute, Some(0));
+    }
+
+    #[test]
+    fn transfer_prefers_dedicated_transfer_then_dedicated_compute() {
+        assert_eq!(resolve(&[(GRAPHICS, 1), (COMPUTE, 1), (TRANSFER, 1)], &[]).unwrap().transfer, Some(2));
+        assert_eq!(resolve(&[(GRAPHICS, 1), (COMPUTE, 1)], &[]).unwrap().transfer, Some(1));
+        assert_eq!(resolve(&[(GRAPHICS, 1)], &[]).unwrap().transfer, Some(0));
+    }
+
+    #[test]
+    fn present_falls_back_to_the_first_presenting_family() {
+        let queue_families = [(GRAPHICS, 1), (COMPUTE, 1), (TRANSFER, 1)];
+        assert_eq!(resolve(&queue_families, &[0, 1]).unwrap().present, Some(0));
+        assert_eq!(resolve(&queue_families, &[2, 1]).unwrap().present, Some(1));
+        assert_eq!(resolve(&queue_families, &[]).unwrap().present, None);
+        assert_eq!(QueueFamilyIndices::resolve(&families(&queue_families), None).unwrap().present, None);
+    }
+
+    #[test]
+    fn unique_lists_each_family_once() {
+        let indices = resolve(&[(GRAPHICS, 1), (COMPUTE, 1), (TRANSFER, 1)], &[0]).unwrap();
+        assert_eq!(indices.unique(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn requires_a_graphics_family() {
+        assert!(matches!(resolve(&[(COMPUTE, 1), (TRANSFER, 1)], &[]), Err(Error::NoGraphicsQueue)));
+        assert!(matches!(resolve(&[(GRAPHICS, 0)], &[]), Err(Error::NoGraphicsQueue)));
+    }
+
+    #[test]
+    fn present_support_errors_are_returned() {
+        let result = QueueFamilyIndices::resolve(&families(&[(GRAPHICS, 1)]), Some(&|_| Err(Error::vulkan("vkGetPhysicalDeviceSurfaceSupportKHR")(vk::Result::ERROR_SURFACE_LOST_KHR))));
+        assert!(matches!(result, Err(Error::Vulkan { result: vk::Result::ERROR_SURFACE_LOST_KHR, .. })));
+    }
+}
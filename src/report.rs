@@ -0,0 +1,136 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use ash::vk;
+use crate::error::Error;
+use crate::gpu::PhysicalDevice;
+use crate::limits::UnmetLimit;
+use crate::profiles::ProfileMiss;
+use crate::util::format_api_version;
+
+/// Why a device was not considered compatible during selection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectionReason {
+    DisallowedType(vk::PhysicalDeviceType),
+    ApiVersionTooLow {
+        required: u32,
+        actual: u32,
+    },
+    LimitNotMet(UnmetLimit),
+    MissingExtensions(Vec<String>),
+    MissingFeatures(Vec<String>),
+    NoPresentQueue,
+    NoSurfaceFormats,
+    NoPresentModes,
+    ProfileNotMet {
+        profile: String,
+        missing: Vec<ProfileMiss>,
+    },
+    CheckerVeto(String),
+    /// A Vulkan call querying the device or its support for the surface failed, e.g. `vkGetPhysicalDeviceSurfaceFormatsKHR`.
+    VulkanCallFailed {
+        call: &'static str,
+        result: vk::Result,
+    },
+    /// Another error prevented checking the device.
+    QueryFailed(String),
+}
+
+impl From<Error> for RejectionReason {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Vulkan { call, result } => RejectionReason::VulkanCallFailed {
+                call,
+                result,
+            },
+            error => RejectionReason::QueryFailed(error.to_string()),
+        }
+    }
+}
+
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectionReason::DisallowedType(device_type) => write!(f, "device type {:?} is not allowed", device_type),
+            RejectionReason::ApiVersionTooLow { required, actual } => write!(f, "Vulkan {} is required but the device supports {}", format_api_version(*required), format_api_version(*actual)),
+            RejectionReason::LimitNotMet(limit) => write!(f, "limit {} is {}, at least {} is required", limit.limit, limit.actual, limit.required),
+            RejectionReason::MissingExtensions(extensions) => write!(f, "missing extensions: {}", extensions.join(", ")),
+            RejectionReason::MissingFeatures(features) => write!(f, "missing features: {}", features.join(", ")),
+            RejectionReason::NoPresentQueue => write!(f, "no queue family can present to the surface"),
+            RejectionReason::NoSurfaceFormats => write!(f, "the surface reports no format for this device"),
+            RejectionReason::NoPresentModes => write!(f, "the surface reports no present mode for this device"),
+            RejectionReason::ProfileNotMet { profile, missing } => {
+                write!(f, "profile {} is not supported: {}", profile, missing.iter().map(ProfileMiss::to_string).collect::<Vec<_>>().join("; "))
+            }
+            RejectionReason::CheckerVeto(checker) => write!(f, "rejected by compatibility checker {}", checker),
+            RejectionReason::VulkanCallFailed { call, result } => write!(f, "the device could not be checked, {} failed: {}", call, result),
+            RejectionReason::QueryFailed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// The outcome of selection for a single enumerated device.
+#[derive(Clone)]
+pub struct DeviceReport {
+    /// Position of the device in the order returned by `vkEnumeratePhysicalDevices`.
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub rejections: Vec<RejectionReason>,
+    /// The score of the device, only computed for compatible devices.
+    pub score: Option<i64>,
+    pub device: Arc<PhysicalDevice>,
+}
+
+impl DeviceReport {
+    pub fn is_compatible(&self) -> bool {
+        self.rejections.is_empty()
+    }
+}
+
+impl Display for DeviceReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} ({:?}): ", self.index, self.name, self.device_type)?;
+        match self.score {
+            Some(score) if self.is_compatible() => writeln!(f, "compatible, score {}", score),
+            _ => {
+                writeln!(f, "rejected")?;
+                for reason in &self.rejections {
+                    writeln!(f, "    - {}", reason)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Every enumerated device and whether it was accepted by a set of selection parameters.
+#[derive(Clone, Default)]
+pub struct SelectionReport {
+    pub devices: Vec<DeviceReport>,
+}
+
+impl SelectionReport {
+    pub fn compatible(&self) -> impl Iterator<Item = &DeviceReport> {
+        self.devices.iter().filter(|device| device.is_compatible())
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = &DeviceReport> {
+        self.devices.iter().filter(|device| !device.is_compatible())
+    }
+}
+
+impl Display for SelectionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "GPU selection report ({} devices):", self.devices.len())?;
+        for device in &self.devices {
+            write!(f, "  {}", device)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for SelectionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
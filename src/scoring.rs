@@ -0,0 +1,115 @@
+use ash::vk;
+use crate::gpu::PhysicalDevice;
+
+/// Gives every compatible device a score during selection. The scores of all scorers are summed and the device with the highest total is selected.
+pub trait GpuScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64;
+}
+
+impl<F: Fn(&PhysicalDevice) -> i64> GpuScorer for F {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        self(physical_device)
+    }
+}
+
+/// Scores a device by its type: by default 4,000,000 for discrete, 3,000,000 for integrated, 2,000,000 for virtual and 1,000,000 for CPU devices, 0 otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceTypeScorer {
+    pub discrete: i64,
+    pub integrated: i64,
+    pub virtual_gpu: i64,
+    pub cpu: i64,
+    pub other: i64,
+}
+
+impl Default for DeviceTypeScorer {
+    fn default() -> Self {
+        Self {
+            discrete: 4_000_000,
+            integrated: 3_000_000,
+            virtual_gpu: 2_000_000,
+            cpu: 1_000_000,
+            other: 0,
+        }
+    }
+}
+
+impl GpuScorer for DeviceTypeScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        match physical_device.device_type() {
+            vk::PhysicalDeviceType::DISCRETE_GPU => self.discrete,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => self.integrated,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => self.virtual_gpu,
+            vk::PhysicalDeviceType::CPU => self.cpu,
+            _ => self.other,
+        }
+    }
+}
+
+/// Scores a device by the size of its largest device local memory heap, one point per MiB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VramScorer;
+
+impl GpuScorer for VramScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        let memory_properties = physical_device.memory_properties();
+        memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize].iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| (heap.size / (1024 * 1024)) as i64)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Scores a device by the Vulkan version usable with it: `(major * 100 + minor) * weight`, so Vulkan 1.3 scores `103 * weight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiVersionScorer {
+    pub weight: i64,
+}
+
+impl Default for ApiVersionScorer {
+    fn default() -> Self {
+        Self {
+            weight: 1,
+        }
+    }
+}
+
+impl GpuScorer for ApiVersionScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        let version = physical_device.api_version();
+        (vk::api_version_major(version) as i64 * 100 + vk::api_version_minor(version) as i64) * self.weight
+    }
+}
+
+/// Scores a device using a function of its limits, e.g. `LimitsScorer(|limits| limits.max_image_dimension2_d as i64)`.
+#[derive(Clone, Copy)]
+pub struct LimitsScorer(pub fn(&vk::PhysicalDeviceLimits) -> i64);
+
+impl GpuScorer for LimitsScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        (self.0)(&physical_device.properties().limits)
+    }
+}
+
+/// Gives a bonus to devices whose name contains the given string (case insensitive), to express a user preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreferenceScorer {
+    name: String,
+    bonus: i64,
+}
+
+impl PreferenceScorer {
+    pub fn new(name: &str, bonus: i64) -> Self {
+        Self {
+            name: name.to_lowercase(),
+            bonus,
+        }
+    }
+}
+
+impl GpuScorer for PreferenceScorer {
+    fn score(&self, physical_device: &PhysicalDevice) -> i64 {
+        if physical_device.name().to_lowercase().contains(&self.name) { self.bonus } else { 0 }
+    }
+}
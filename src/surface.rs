@@ -1,52 +1,113 @@
+use std::sync::Arc;
+use ash::extensions::{ext, khr};
 use ash::vk;
+use crate::debug::set_object_name;
+use crate::device::Device;
+use crate::error::{Error, Result};
+use crate::gpu::PhysicalDevice;
 use crate::instance::Instance;
+use crate::util::HandleOwnership;
 
+/// A window surface. It keeps its instance alive and destroys the surface when dropped.
 pub struct Surface {
+    instance: Arc<Instance>,
+    surface_fn: khr::Surface,
     surface: vk::SurfaceKHR,
+    ownership: HandleOwnership,
 }
 
 /// Provides a way to create a vulkan surface object
 pub trait SurfaceProvider {
     /// Create a surface from self and the provided instance.
-    fn create_surface_raw(&self, instance: &Instance) -> Option<vk::SurfaceKHR>;
-}
-
-#[derive(Debug)]
-pub enum SurfaceInitError {
-    CreateSurfaceError,
+    fn create_surface_raw(&self, instance: &Instance) -> Result<vk::SurfaceKHR>;
 }
 
 impl Surface {
-    pub fn new(instance: &Instance, surface_provider: &dyn SurfaceProvider) -> Result<Self, SurfaceInitError> {
-        match surface_provider.create_surface_raw(instance) {
-            Some(surface) => {
-                Ok(Self {
-                    surface,
-                })
-            },
-            None => Err(SurfaceInitError::CreateSurfaceError),
-        }
+    pub fn new(instance: Arc<Instance>, surface_provider: &dyn SurfaceProvider) -> Result<Self> {
+        let surface = surface_provider.create_surface_raw(&instance)?;
+        Ok(unsafe { Self::from_raw(instance, surface, HandleOwnership::Owned) })
     }
 
     /// Use this function when using glfw to make sure that you use the glfw logic for creating the surface. otherwise it will switch to the builtin logic for raw-window-handle (if glfw-rs ever updates to raw-window-handle 0.6.0, as of yet it has not), or it might just not work.
     #[cfg(feature = "glfw")]
-    pub fn new_glfw(instance: &Instance, window: &glfw::Window) -> Result<Self, SurfaceInitError> {
-        match create_surface_glfw(window, instance) {
-            Some(surface) => {
-                Ok(Self {
-                    surface,
-                })
-            },
-            None => Err(SurfaceInitError::CreateSurfaceError),
+    pub fn new_glfw(instance: Arc<Instance>, window: &glfw::Window) -> Result<Self> {
+        let surface = create_surface_glfw(window, &instance)?;
+        Ok(unsafe { Self::from_raw(instance, surface, HandleOwnership::Owned) })
+    }
+
+    /// Create a surface not tied to any window. The instance must have been created with `InstanceBuilder::headless_surface`.
+    pub fn new_headless(instance: Arc<Instance>) -> Result<Self> {
+        let extension_name = ext::HeadlessSurface::name().to_string_lossy();
+        if !instance.enabled_extensions().iter().any(|extension| *extension == extension_name) {
+            return Err(Error::MissingInstanceExtensions(vec![extension_name.into_owned()]));
         }
+
+        let surface_fn = ext::HeadlessSurface::new(instance.entry(), instance.handle());
+        let surface = unsafe { surface_fn.create_headless_surface(&vk::HeadlessSurfaceCreateInfoEXT::default(), instance.allocation_callbacks()) }
+            .map_err(Error::vulkan("vkCreateHeadlessSurfaceEXT"))?;
+        Ok(unsafe { Self::from_raw(instance, surface, HandleOwnership::Owned) })
     }
 
-    // pub fn enumerate_surface_formats(&self, gpu: &PhysicalDevice) -> VkResult<Vec<vk::SurfaceFormatKHR>> {
-    // }
+    /// Wrap a surface created elsewhere from `instance`. With `HandleOwnership::Owned` the surface is destroyed when the wrapper is dropped.
+    ///
+    /// # Safety
+    /// `surface` must be a valid surface created from `instance`. With `HandleOwnership::Owned` nothing else may destroy it.
+    pub unsafe fn from_raw(instance: Arc<Instance>, surface: vk::SurfaceKHR, ownership: HandleOwnership) -> Self {
+        Self {
+            surface_fn: khr::Surface::new(instance.entry(), instance.handle()),
+            instance,
+            surface,
+            ownership,
+        }
+    }
+
+    pub fn instance(&self) -> &Arc<Instance> {
+        &self.instance
+    }
+
+    pub fn formats(&self, physical_device: &PhysicalDevice) -> Result<Vec<vk::SurfaceFormatKHR>> {
+        unsafe {
+            self.surface_fn.get_physical_device_surface_formats(physical_device.handle(), self.surface)
+        }.map_err(Error::vulkan("vkGetPhysicalDeviceSurfaceFormatsKHR"))
+    }
+
+    pub fn present_modes(&self, physical_device: &PhysicalDevice) -> Result<Vec<vk::PresentModeKHR>> {
+        unsafe {
+            self.surface_fn.get_physical_device_surface_present_modes(physical_device.handle(), self.surface)
+        }.map_err(Error::vulkan("vkGetPhysicalDeviceSurfacePresentModesKHR"))
+    }
+
+    pub fn capabilities(&self, physical_device: &PhysicalDevice) -> Result<vk::SurfaceCapabilitiesKHR> {
+        unsafe {
+            self.surface_fn.get_physical_device_surface_capabilities(physical_device.handle(), self.surface)
+        }.map_err(Error::vulkan("vkGetPhysicalDeviceSurfaceCapabilitiesKHR"))
+    }
+
+    /// Whether the given queue family of the device can present to this surface.
+    pub fn supports_present(&self, physical_device: &PhysicalDevice, queue_family_index: u32) -> Result<bool> {
+        unsafe {
+            self.surface_fn.get_physical_device_surface_support(physical_device.handle(), queue_family_index, self.surface)
+        }.map_err(Error::vulkan("vkGetPhysicalDeviceSurfaceSupportKHR"))
+    }
 
     pub fn surface_handle(&self) -> vk::SurfaceKHR {
         self.surface
     }
+
+    /// Name the surface for validation messages and debugging tools. Naming goes through a device created from the same instance, and does nothing without `VK_EXT_debug_utils`.
+    pub fn set_debug_name(&self, device: &Device, name: &str) -> Result<()> {
+        set_object_name(self.instance.debug_utils(), device.handle().handle(), self.surface, name)
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        if self.ownership == HandleOwnership::Owned {
+            unsafe {
+                self.surface_fn.destroy_surface(self.surface, self.instance.allocation_callbacks());
+            }
+        }
+    }
 }
 
 // Window creation crate integrations
@@ -54,17 +115,18 @@ impl Surface {
 // the next 2 functions are a workaround due to conflicting implementations of SurfaceProvider when both features are enabled.
 #[cfg(all(feature = "glfw", not(feature="raw-window-handle")))]
 impl SurfaceProvider for glfw::Window {
-    fn create_surface_raw(&self, instance: &Instance) -> Option<vk::SurfaceKHR> {
+    fn create_surface_raw(&self, instance: &Instance) -> Result<vk::SurfaceKHR> {
         create_surface_glfw(self, instance)
     }
 }
 
 #[cfg(feature = "glfw")]
-fn create_surface_glfw(window: &glfw::Window, instance: &Instance) -> Option<vk::SurfaceKHR> {
+fn create_surface_glfw(window: &glfw::Window, instance: &Instance) -> Result<vk::SurfaceKHR> {
     let mut surf = vk::SurfaceKHR::null();
-    match window.create_window_surface(instance.handle().handle(), std::ptr::null(), &mut surf) {
-        vk::Result::SUCCESS => Some(surf),
-        e => None,
+    let allocation_callbacks = instance.allocation_callbacks().map_or(std::ptr::null(), |callbacks| callbacks as *const _);
+    match window.create_window_surface(instance.handle().handle(), allocation_callbacks, &mut surf) {
+        vk::Result::SUCCESS => Ok(surf),
+        result => Err(Error::vulkan("glfwCreateWindowSurface")(result)),
     }
 }
 
@@ -74,39 +136,49 @@ fn create_surface_glfw(window: &glfw::Window, instance: &Instance) -> Option<vk:
 mod os_raw {
     use ash::vk;
     use ash::vk::SurfaceKHR;
-    use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
-    use crate::instance::Instance;
+    use raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
+    use crate::error::{Error, Result};
+    use crate::instance::{Instance, window_system_name};
     use crate::surface::SurfaceProvider;
 
+    fn invalid_handle(window_handle: &RawWindowHandle, reason: &str) -> Error {
+        Error::InvalidWindowHandle {
+            platform: window_system_name(window_handle).to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn handle_error(error: HandleError) -> Error {
+        Error::InvalidWindowHandle {
+            platform: "raw-window-handle".to_string(),
+            reason: error.to_string(),
+        }
+    }
+
     impl<T: HasWindowHandle + HasDisplayHandle> SurfaceProvider for T {
-        fn create_surface_raw(&self, instance: &Instance) -> Option<SurfaceKHR> {
-            match (self.window_handle().unwrap().as_raw(), self.display_handle().unwrap().as_raw()) {
-                (RawWindowHandle::UiKit(_), RawDisplayHandle::UiKit(_)) => None,
-                (RawWindowHandle::AppKit(_), RawDisplayHandle::AppKit(_)) => None,
-                (RawWindowHandle::Orbital(_), RawDisplayHandle::Orbital(_)) => None,
-                (RawWindowHandle::Drm(_), RawDisplayHandle::Drm(_)) => None,
-                (RawWindowHandle::Gbm(_), RawDisplayHandle::Gbm(_)) => None,
-                (RawWindowHandle::WinRt(_), RawDisplayHandle::Windows(_)) => None,
-                (RawWindowHandle::Web(_), RawDisplayHandle::Web(_)) => None,
-                (RawWindowHandle::WebCanvas(_), RawDisplayHandle::Web(_)) => None,
-                (RawWindowHandle::WebOffscreenCanvas(_), RawDisplayHandle::Web(_)) => None,
-                (RawWindowHandle::Haiku(_), RawDisplayHandle::Haiku(_)) => None,
+        fn create_surface_raw(&self, instance: &Instance) -> Result<SurfaceKHR> {
+            let window_handle = self.window_handle().map_err(handle_error)?.as_raw();
+            let display_handle = self.display_handle().map_err(handle_error)?.as_raw();
+
+            match (window_handle, display_handle) {
                 (RawWindowHandle::Xlib(wh), RawDisplayHandle::Xlib(dh)) => {
+                    let display = dh.display.ok_or_else(|| invalid_handle(&window_handle, "the Xlib display is missing"))?;
                     unsafe {
                         let sci = vk::XlibSurfaceCreateInfoKHR::builder()
                             .window(wh.window)
-                            .dpy(dh.display.unwrap().as_ptr() as *mut _);
+                            .dpy(display.as_ptr() as *mut _);
                         let surface_fn = ash::extensions::khr::XlibSurface::new(instance.entry(), instance.handle());
-                        return surface_fn.create_xlib_surface(&sci, None).map_or(None, |s| Some(s));
+                        surface_fn.create_xlib_surface(&sci, instance.allocation_callbacks()).map_err(Error::vulkan("vkCreateXlibSurfaceKHR"))
                     }
                 },
                 (RawWindowHandle::Xcb(wh), RawDisplayHandle::Xcb(dh)) => {
+                    let connection = dh.connection.ok_or_else(|| invalid_handle(&window_handle, "the XCB connection is missing"))?;
                     unsafe {
                         let sci = vk::XcbSurfaceCreateInfoKHR::builder()
                             .window(wh.window.get() as _)
-                            .connection(dh.connection.unwrap().as_ptr() as _);
+                            .connection(connection.as_ptr() as _);
                         let surface_fn = ash::extensions::khr::XcbSurface::new(instance.entry(), instance.handle());
-                        return surface_fn.create_xcb_surface(&sci, None).map_or(None, |s| Some(s));
+                        surface_fn.create_xcb_surface(&sci, instance.allocation_callbacks()).map_err(Error::vulkan("vkCreateXcbSurfaceKHR"))
                     }
                 },
                 (RawWindowHandle::Wayland(wh), RawDisplayHandle::Wayland(dh)) => {
@@ -115,16 +187,17 @@ mod os_raw {
                             .display(dh.display.as_ptr() as _)
                             .surface(wh.surface.as_ptr() as _);
                         let surface_fn = ash::extensions::khr::WaylandSurface::new(instance.entry(), instance.handle());
-                        return surface_fn.create_wayland_surface(&sci, None).map_or(None, |s| Some(s));
+                        surface_fn.create_wayland_surface(&sci, instance.allocation_callbacks()).map_err(Error::vulkan("vkCreateWaylandSurfaceKHR"))
                     }
                 },
                 (RawWindowHandle::Win32(wh), RawDisplayHandle::Windows(_)) => {
+                    let hinstance = wh.hinstance.ok_or_else(|| invalid_handle(&window_handle, "the HINSTANCE is missing"))?;
                     unsafe {
                         let sci = vk::Win32SurfaceCreateInfoKHR::builder()
                             .hwnd(wh.hwnd.get() as _)
-                            .hinstance(wh.hinstance.unwrap().get() as _);
+                            .hinstance(hinstance.get() as _);
                         let surface_fn = ash::extensions::khr::Win32Surface::new(instance.entry(), instance.handle());
-                        return surface_fn.create_win32_surface(&sci, None).map_or(None, |s| Some(s));
+                        surface_fn.create_win32_surface(&sci, instance.allocation_callbacks()).map_err(Error::vulkan("vkCreateWin32SurfaceKHR"))
                     }
                 },
                 (RawWindowHandle::AndroidNdk(wh), RawDisplayHandle::Android(_)) => {
@@ -132,11 +205,13 @@ mod os_raw {
                         let sci = vk::AndroidSurfaceCreateInfoKHR::builder()
                             .window(wh.a_native_window.as_ptr() as _);
                         let surface_fn = ash::extensions::khr::AndroidSurface::new(instance.entry(), instance.handle());
-                        return surface_fn.create_android_surface(&sci, None).map_or(None, |s| Some(s));
+                        surface_fn.create_android_surface(&sci, instance.allocation_callbacks()).map_err(Error::vulkan("vkCreateAndroidSurfaceKHR"))
                     }
                 },
-                _ => None
+                _ => Err(Error::UnsupportedPlatform {
+                    platform: window_system_name(&window_handle).to_string(),
+                }),
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,21 @@
+use ash::vk;
+
+/// Format a version packed with `vk::make_api_version` as `major.minor.patch`.
+pub fn format_api_version(version: u32) -> String {
+    format!("{}.{}.{}", vk::api_version_major(version), vk::api_version_minor(version), vk::api_version_patch(version))
+}
+
+/// Format a UUID as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: Vec<String> = uuid.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", hex[0..4].concat(), hex[4..6].concat(), hex[6..8].concat(), hex[8..10].concat(), hex[10..16].concat())
+}
+
+/// Whether a wrapper destroys its Vulkan handle when dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleOwnership {
+    /// The handle was created by graphicat and is destroyed on drop.
+    Owned,
+    /// The handle was created elsewhere and imported, whoever created it is responsible for destroying it.
+    Imported,
+}